@@ -1,8 +1,159 @@
+use std::collections::HashSet;
+use std::path::Path;
 use std::process::Command;
 
+use serde::Deserialize;
+
+#[path = "src/features.rs"]
+mod features;
+
 fn main() {
     Command::new("bash")
-        .args(&["./bin/buildjs.sh"])
+        .args(["./bin/buildjs.sh"])
         .output()
         .expect("failed to build javascript");
+
+    let errors = validate_manifests();
+    if !errors.is_empty() {
+        panic!("invalid manifests:\n  {}", errors.join("\n  "));
+    }
+}
+
+// Just enough of the shapes in src/render.rs, src/dependencies.rs and src/main.rs to read the
+// manifests; serde ignores the fields we don't check.
+#[derive(Deserialize)]
+enum Node {
+    Dir(DirSpec),
+    File(FileSpec),
+    Template(TemplateSpec),
+}
+
+#[derive(Deserialize)]
+struct DirSpec {
+    children: Vec<(String, u32, Node, Option<When>)>,
+}
+
+#[derive(Deserialize)]
+struct FileSpec {}
+
+#[derive(Deserialize)]
+struct TemplateSpec {
+    template_name: String,
+}
+
+#[derive(Deserialize)]
+struct When {
+    #[serde(default)]
+    all_of: Vec<String>,
+    #[serde(default)]
+    none_of: Vec<String>,
+    #[serde(default)]
+    any_of: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DependencyType {
+    Normal,
+    Development,
+    Types,
+    Peer,
+    Optional,
+    Override,
+}
+
+#[derive(Deserialize)]
+struct DependencySpec {
+    name: String,
+    kind: DependencyType,
+    preconditions: Option<When>,
+}
+
+#[derive(Deserialize)]
+struct RunScriptSpec {
+    key: String,
+    preconditions: Option<When>,
+}
+
+fn validate_manifests() -> Vec<String> {
+    let mut errors = Vec::new();
+    let features: HashSet<&str> = features::FEATURES.iter().copied().collect();
+
+    let mut check_when = |errors: &mut Vec<String>, what: &str, when: &Option<When>| {
+        let when = match when {
+            Some(when) => when,
+            None => return,
+        };
+        for feature in when.all_of.iter().chain(&when.none_of).chain(&when.any_of) {
+            if !features.contains(feature.as_str()) {
+                errors.push(format!("{} depends on unknown feature \"{}\"", what, feature));
+            }
+        }
+    };
+
+    match ron::de::from_str::<Node>(include_str!("src/dirspec.ron")) {
+        Ok(root) => validate_node(&root, "", &mut errors, &mut check_when),
+        Err(err) => errors.push(format!("src/dirspec.ron: {}", err)),
+    }
+
+    match ron::de::from_str::<Vec<DependencySpec>>(include_str!("src/dependencies.ron")) {
+        Ok(specs) => {
+            let mut seen = HashSet::new();
+            for spec in specs {
+                // Override entries share names with the packages they pin.
+                if !seen.insert((spec.name.clone(), spec.kind)) {
+                    errors.push(format!(
+                        "src/dependencies.ron lists {} ({:?}) more than once; merge the entries' preconditions",
+                        spec.name, spec.kind
+                    ));
+                }
+                let what = format!("src/dependencies.ron: {}", spec.name);
+                check_when(&mut errors, &what, &spec.preconditions);
+            }
+        }
+        Err(err) => errors.push(format!("src/dependencies.ron: {}", err)),
+    }
+
+    match ron::de::from_str::<Vec<RunScriptSpec>>(include_str!("src/runscripts.ron")) {
+        Ok(specs) => {
+            for spec in specs {
+                let what = format!("src/runscripts.ron: {}", spec.key);
+                check_when(&mut errors, &what, &spec.preconditions);
+            }
+        }
+        Err(err) => errors.push(format!("src/runscripts.ron: {}", err)),
+    }
+
+    errors
+}
+
+fn validate_node(
+    node: &Node,
+    path: &str,
+    errors: &mut Vec<String>,
+    check_when: &mut impl FnMut(&mut Vec<String>, &str, &Option<When>),
+) {
+    match node {
+        Node::Dir(spec) => {
+            for (name, _, child, when) in &spec.children {
+                let child_path = format!("{}/{}", path, name);
+                check_when(errors, &format!("src/dirspec.ron: {}", child_path), when);
+                validate_node(child, &child_path, errors, check_when);
+            }
+        }
+        Node::File(_) => {}
+        Node::Template(spec) => {
+            // The JavaScript templates are compiled from the TypeScript ones by bin/buildjs.sh,
+            // which can fail (say, offline) without failing the build; check their source.
+            let template = match spec.template_name.strip_prefix("boltzmann-js/") {
+                Some(rest) => format!("boltzmann/{}", rest),
+                None => spec.template_name.clone(),
+            };
+            if !Path::new("templates").join(&template).is_file() {
+                errors.push(format!(
+                    "src/dirspec.ron: {} uses missing template \"{}\"",
+                    path, spec.template_name
+                ));
+            }
+        }
+    }
 }
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context as ErrorContext, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::errors::Failure;
+use super::Settings;
+
+/// Per-user settings, from `boltzmann/config.toml` in the user's config directory.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // --preset groups, by name; they win over the built-in ones.
+    #[serde(default)]
+    pub(crate) presets: BTreeMap<String, Vec<String>>,
+
+    // Settings for projects Boltzmann hasn't scaffolded yet, keyed as in package.json.
+    #[serde(default)]
+    pub(crate) defaults: Settings,
+}
+
+impl Config {
+    /// Layer the user's defaults over `settings`, the ones we'd give a new project otherwise.
+    pub fn defaults_over(&self, settings: Settings) -> Result<Settings> {
+        let mut defaults = self.defaults.clone();
+        defaults.version = None;
+        // Left for the caller, since a lockfile in the project should win over it.
+        defaults.package_manager = None;
+        // Not serialized, so spell the old `volta = true` the new way.
+        if defaults.volta.is_some() && defaults.node_manager.is_none() {
+            defaults.node_manager = Some(defaults.node_manager());
+        }
+
+        let mut merged = serde_json::to_value(settings)?;
+        if let (Value::Object(merged), Value::Object(defaults)) =
+            (&mut merged, serde_json::to_value(defaults)?)
+        {
+            merged.extend(defaults);
+        }
+        Ok(serde_json::from_value(merged)?)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/boltzmann/config.toml`, falling back to `~/.config` like most CLIs do,
+/// even on macOS.
+pub fn path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Some(base.join("boltzmann").join("config.toml"))
+}
+
+/// Read the user's config; not having one is fine, but a broken one is an error.
+pub fn load() -> Result<Config> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(err).context(Failure::InvalidConfig(path)),
+    };
+    let config: Config = toml::from_str(&contents).context(Failure::InvalidConfig(path.clone()))?;
+
+    // Settings keeps keys it doesn't know, for package.json's sake; here they're typos.
+    if let Some(key) = config.defaults.rest.keys().next() {
+        return Err(anyhow!("unknown setting `{}` in [defaults]", key))
+            .context(Failure::InvalidConfig(path));
+    }
+    Ok(config)
+}
+
+#[test]
+fn check_defaults_over() {
+    use super::settings::{NodeManager, PackageManager};
+
+    let config: Config = toml::from_str(
+        r#"
+        [defaults]
+        githubci = false
+        typescript = true
+        node_version = "18"
+        version = "0.1.0"
+        package_manager = "pnpm"
+        volta = true
+        "#,
+    )
+    .unwrap();
+    let base = Settings {
+        githubci: Some(true),
+        status: Some(true),
+        ..Default::default()
+    };
+
+    // The user's defaults win over ours; ours fill in the rest.
+    let merged = config.defaults_over(base.clone()).unwrap();
+    assert_eq!(merged.githubci, Some(false));
+    assert_eq!(merged.status, Some(true));
+    assert_eq!(merged.typescript, Some(true));
+    assert_eq!(merged.node_version.as_deref(), Some("18"));
+    assert_eq!(merged.node_manager, Some(NodeManager::Volta));
+    // The version is ours to record, and the package manager is up to the lockfile first.
+    assert_eq!(merged.version, None);
+    assert_eq!(merged.package_manager, None);
+    assert_eq!(config.defaults.package_manager, Some(PackageManager::Pnpm));
+
+    // An explicit node_manager wins over the old volta setting.
+    let config: Config = toml::from_str("[defaults]\nvolta = true\nnode_manager = \"fnm\"\n").unwrap();
+    let merged = config.defaults_over(base).unwrap();
+    assert_eq!(merged.node_manager, Some(NodeManager::Fnm));
+}
@@ -0,0 +1,253 @@
+use anyhow::Result;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use super::{Settings, When};
+
+// data structures for dep lists
+#[derive(Deserialize, Clone, Copy)]
+pub enum DependencyType {
+    Normal,
+    Development,
+    /// Type definitions: a dependency of TypeScript projects that compile at runtime, and a
+    /// devDependency otherwise.
+    Types,
+    Peer,
+    Optional,
+    /// An entry in package.json's `overrides` (`pnpm.overrides` for pnpm, `resolutions` for
+    /// yarn), forcing the range resolved for a transitive dependency. Used to settle peer
+    /// dependency conflicts between packages we install.
+    Override,
+}
+
+impl ::std::fmt::Display for DependencyType {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match *self {
+            DependencyType::Normal => f.write_str(""),
+            DependencyType::Development => f.write_str("(dev)"),
+            DependencyType::Types => f.write_str("(types)"),
+            DependencyType::Peer => f.write_str("(peer)"),
+            DependencyType::Optional => f.write_str("(optional)"),
+            DependencyType::Override => f.write_str("(override)"),
+        }
+    }
+}
+
+impl DependencyType {
+    /// The package.json field that lists dependencies of this kind.
+    pub fn field(&self) -> &'static str {
+        match self {
+            DependencyType::Normal | DependencyType::Types => "dependencies",
+            DependencyType::Development => "devDependencies",
+            DependencyType::Peer => "peerDependencies",
+            DependencyType::Optional => "optionalDependencies",
+            DependencyType::Override => "overrides",
+        }
+    }
+
+    /// The kind to install as, given where this project wants its type definitions.
+    pub fn resolve(self, typings_are_dev: bool) -> DependencyType {
+        match self {
+            DependencyType::Types if typings_are_dev => DependencyType::Development,
+            DependencyType::Types => DependencyType::Normal,
+            kind => kind,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VersionedDependency {
+    version: Version,
+    range: String,
+}
+
+#[derive(Deserialize)]
+pub struct DependencySpec {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) kind: DependencyType,
+    pub(crate) preconditions: Option<When>,
+    /// The ranges earlier releases of Boltzmann installed, keyed by the release that introduced
+    /// each one.
+    #[serde(default)]
+    pub(crate) versions: Vec<VersionedDependency>,
+    /// The release that stopped using this package. Retired packages are never installed; they
+    /// are removed from projects that still have the range an earlier release gave them.
+    #[serde(default)]
+    pub(crate) retired: Option<Version>,
+}
+
+impl DependencySpec {
+    /// The range a project last scaffolded by `version` would have been given, if any.
+    pub fn installed_by(&self, version: &Version) -> Option<&str> {
+        self.versions
+            .iter()
+            .filter(|entry| &entry.version <= version)
+            .max_by(|left, right| left.version.cmp(&right.version))
+            .map(|entry| entry.range.as_str())
+    }
+}
+
+/// How a project overrides our management of one dependency, set in the `dependencies` map of
+/// its `boltzmann` settings: `false` leaves the package alone entirely, while a string is used in
+/// place of our range (a pinned version, or an `npm:` alias for a fork.)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum DependencyOverride {
+    Managed(bool),
+    Range(String),
+}
+
+pub fn candidates() -> Result<Vec<DependencySpec>> {
+    Ok(ron::de::from_str(include_str!("dependencies.ron"))?)
+}
+
+/// Every package Boltzmann manages for the features `settings` enables, minus those the project
+/// has opted out of. Names that vary with the Node.js version are returned as globs, e.g.
+/// `@tsconfig/node*`.
+pub fn managed_names(settings: &Settings) -> Result<Vec<String>> {
+    let enabled = serde_json::to_value(settings)?;
+    let mut names: Vec<String> = candidates()?
+        .into_iter()
+        .filter(|candidate| candidate.retired.is_none())
+        .filter(|candidate| {
+            candidate
+                .preconditions
+                .as_ref()
+                .map(|preconditions| preconditions.are_satisfied_by(&enabled))
+                .unwrap_or(true)
+        })
+        .filter(|candidate| {
+            settings.dependency_override(&candidate.name) != Some(&DependencyOverride::Managed(false))
+        })
+        .map(|candidate| candidate.name.replace("{node_version}", "*"))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    Ok(names)
+}
+
+// The lowest version an npm range admits, for the simple ranges we write ourselves and people
+// tend to bump them to: `^8.9.0`, `~8.9`, `>=8.9.0`, `8.9.x`. Anything fancier returns None.
+fn minimum_version(range: &str) -> Option<Version> {
+    let range = range.trim();
+    if range.contains(' ') || range.contains("||") || range.starts_with('<') {
+        return None;
+    }
+
+    let bare = range.trim_start_matches(['^', '~', '>', '=', 'v']);
+    if let Ok(version) = Version::parse(bare) {
+        return Some(version);
+    }
+
+    let mut parts = [0u64; 3];
+    for (idx, part) in bare.split('.').enumerate() {
+        if idx > 2 {
+            return None;
+        }
+        parts[idx] = match part {
+            "x" | "X" | "*" => break,
+            _ => part.parse().ok()?,
+        };
+    }
+    Some(Version::new(parts[0], parts[1], parts[2]))
+}
+
+/// True if `existing` is a range that starts at or above the minimum of `spec` while staying
+/// compatible with it; e.g. `^8.9.0` for a spec of `^8.6.0`, but not `^7.0.0` or `^9.0.0`.
+pub fn satisfies_spec(existing: &str, spec: &str) -> bool {
+    let requirement = match VersionReq::parse(spec) {
+        Ok(requirement) => requirement,
+        Err(_) => return false,
+    };
+
+    match (minimum_version(existing), minimum_version(spec)) {
+        (Some(existing), Some(minimum)) => existing >= minimum && requirement.matches(&existing),
+        _ => false,
+    }
+}
+
+/// What to do with a retired package that a project lists.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Retirement {
+    /// It's at the range an earlier release gave it, so it's ours to remove.
+    Remove,
+    /// We installed it once, but someone has changed the range since; say so and leave it.
+    LeaveInPlace,
+    /// It was never ours: the project was scaffolded after the package retired, or never at all,
+    /// or the package is overridden.
+    Ignore,
+}
+
+impl DependencySpec {
+    /// Decide what happens to this retired package in a project last scaffolded by
+    /// `scaffolded_by` (None if Boltzmann has never run there), which lists it at `current`.
+    pub fn retirement(
+        &self,
+        scaffolded_by: Option<&Version>,
+        current: &str,
+        overridden: bool,
+    ) -> Retirement {
+        match (&self.retired, scaffolded_by) {
+            (Some(retired), Some(scaffolded_by)) if scaffolded_by < retired && !overridden => {
+                if self.installed_by(scaffolded_by) == Some(current) {
+                    Retirement::Remove
+                } else {
+                    Retirement::LeaveInPlace
+                }
+            }
+            _ => Retirement::Ignore,
+        }
+    }
+}
+
+#[test]
+fn check_satisfies_spec() {
+    assert!(satisfies_spec("^8.9.0", "^8.6.0"));
+    assert!(satisfies_spec("8.9.1", "^8.6.0"));
+    assert!(satisfies_spec("~8.6", "^8.6.0"));
+    assert!(satisfies_spec("^0.27.3", "^0.27.0"));
+    assert!(!satisfies_spec("^8.5.0", "^8.6.0"));
+    assert!(!satisfies_spec("^9.0.0", "^8.6.0"));
+    assert!(!satisfies_spec("^0.28.0", "^0.27.0"));
+    assert!(!satisfies_spec("latest", "^8.6.0"));
+    assert!(!satisfies_spec(">=8.6.0 <9", "^8.6.0"));
+}
+
+#[test]
+fn check_retirement() {
+    let spec: DependencySpec = ron::de::from_str(
+        r#"DependencySpec(
+            name: "honeycomb-beeline",
+            version: "^2.7.4",
+            kind: Normal,
+            preconditions: Some(When(all_of: ["honeycomb"])),
+            versions: [
+                VersionedDependency(version: "0.1.0", range: "^2.2.0"),
+                VersionedDependency(version: "0.4.0", range: "^2.7.4"),
+            ],
+            retired: Some("0.5.0"),
+        )"#,
+    )
+    .unwrap();
+    let version = |version: &str| Version::parse(version).unwrap();
+
+    // The range the last release to scaffold the project gave it.
+    assert_eq!(spec.retirement(Some(&version("0.4.2")), "^2.7.4", false), Retirement::Remove);
+    assert_eq!(spec.retirement(Some(&version("0.3.0")), "^2.2.0", false), Retirement::Remove);
+
+    // Someone has changed the range since, or pinned it in their settings.
+    assert_eq!(spec.retirement(Some(&version("0.3.0")), "^2.7.4", false), Retirement::LeaveInPlace);
+    assert_eq!(spec.retirement(Some(&version("0.4.2")), "^2.7.4", true), Retirement::Ignore);
+
+    // Added by hand after we stopped installing it, or to a project we've never scaffolded.
+    assert_eq!(spec.retirement(Some(&version("0.5.0")), "^2.7.4", false), Retirement::Ignore);
+    assert_eq!(spec.retirement(None, "^2.2.0", false), Retirement::Ignore);
+
+    // Never retired.
+    let current: DependencySpec = ron::de::from_str(
+        r#"DependencySpec(name: "bole", version: "^4.0.0", kind: Normal, preconditions: None)"#,
+    )
+    .unwrap();
+    assert_eq!(current.retirement(Some(&version("0.1.0")), "^4.0.0", false), Retirement::Ignore);
+}
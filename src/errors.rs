@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// The failures a wrapping script might want to tell apart, each with its own exit code. They're
+/// either returned directly or attached as `anyhow` context, so `exit_code` finds them either way.
+/// Anything else exits with 1.
+#[derive(Error, Debug)]
+pub enum Failure {
+    /// Options that clap accepted but we can't act on; clap exits with 2 for the rest.
+    #[error("{0}")]
+    Usage(String),
+    #[error("git working directory is dirty; pass --force if you want to run anyway")]
+    DirtyTree,
+    #[error("Failed to read {0:?}; fix it by hand and run again")]
+    InvalidPackageJson(PathBuf),
+    #[error("Failed to render Boltzmann files")]
+    Render,
+    #[error("Failed to install dependencies")]
+    Install,
+    #[error("Failed to read {0:?}; fix it by hand and run again")]
+    InvalidConfig(PathBuf),
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Usage(_) => 2,
+            Failure::DirtyTree => 3,
+            Failure::InvalidPackageJson(_) => 4,
+            Failure::Render => 5,
+            Failure::Install => 6,
+            Failure::InvalidConfig(_) => 7,
+        }
+    }
+}
+
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<Failure>()
+        .map(Failure::exit_code)
+        .unwrap_or(1)
+}
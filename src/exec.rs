@@ -0,0 +1,103 @@
+use std::io::prelude::*;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context as ErrorContext, Result};
+use atty::Stream;
+use log::error;
+use owo_colors::OwoColorize;
+use subprocess::{Exec, ExitStatus, Redirection};
+
+// Written to the project directory while a command runs, and kept only if it fails.
+const LOG_FILE: &str = "boltzmann-debug.log";
+const TAIL_LINES: usize = 20;
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Run `command` in `cwd`, describing it as `label`. At -vv and up its output goes straight to
+/// the terminal; otherwise it's captured to a log file, with a spinner while we wait, and the
+/// tail of stderr is printed if the command fails.
+pub fn run(command: Exec, label: &str, cwd: &Path, verbosity: u64) -> Result<()> {
+    let command = command.cwd(cwd);
+    // Verbosity counts from 1 when no -v is passed.
+    if verbosity >= 3 {
+        let status = command
+            .join()
+            .with_context(|| format!("Failed to run `{}`; is it installed?", label))?;
+        return check(status, label, None);
+    }
+
+    let log_path = cwd.join(LOG_FILE);
+    let _ = std::fs::remove_file(&log_path); // left over from an earlier failure
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("Failed to create {:?}", log_path))?;
+    let mut stderr_log = log.try_clone()?;
+
+    let mut process = command
+        .stdout(Redirection::File(log))
+        .stderr(Redirection::Pipe)
+        .popen()
+        .with_context(|| format!("Failed to run `{}`; is it installed?", label))?;
+
+    // Drain stderr on its own thread so a chatty command can't fill the pipe and stall.
+    let mut stderr = process.stderr.take().expect("stderr is piped");
+    let reader = std::thread::spawn(move || -> Vec<u8> {
+        let mut captured = Vec::new();
+        let mut chunk = [0; 4096];
+        while let Ok(count) = stderr.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            captured.extend_from_slice(&chunk[..count]);
+            let _ = stderr_log.write_all(&chunk[..count]);
+        }
+        captured
+    });
+
+    let spin = verbosity > 0 && atty::is(Stream::Stdout);
+    let mut frame = 0;
+    let status = loop {
+        if let Some(status) = process.wait_timeout(Duration::from_millis(100))? {
+            break status;
+        }
+        if spin {
+            print!("\r    {} {}", SPINNER[frame % SPINNER.len()].blue(), label);
+            std::io::stdout().flush()?;
+            frame += 1;
+        }
+    };
+    if spin {
+        print!("\r\x1b[2K");
+        std::io::stdout().flush()?;
+    }
+
+    let captured = reader.join().unwrap_or_default();
+    if status.success() {
+        let _ = std::fs::remove_file(&log_path);
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&captured);
+    let lines: Vec<&str> = stderr.lines().collect();
+    for line in &lines[lines.len().saturating_sub(TAIL_LINES)..] {
+        error!("    {}", line);
+    }
+    check(status, label, Some(&log_path))
+}
+
+fn check(status: ExitStatus, label: &str, log_path: Option<&Path>) -> Result<()> {
+    match (status, log_path) {
+        (ExitStatus::Exited(0), _) => Ok(()),
+        (_, Some(log_path)) => Err(anyhow!(
+            "`{}` exited with non-zero status; see {} for its full output.",
+            label,
+            log_path.display()
+        )),
+        (_, None) => Err(anyhow!(
+            "`{}` exited with non-zero status; run by hand to diagnose.",
+            label
+        )),
+    }
+}
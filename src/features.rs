@@ -0,0 +1,26 @@
+/// Every feature a project can turn on: each is an `Option<bool>` field of `Settings`, and the
+/// names manifest preconditions may use. build.rs includes this file to check the manifests.
+pub const FEATURES: &[&str] = &[
+    "compose",
+    "csrf",
+    "dependabot",
+    "esbuild",
+    "githubci",
+    "githubci_container",
+    "githubci_release",
+    "gitlabci",
+    "honeycomb",
+    "jwt",
+    "kubernetes",
+    "livereload",
+    "oauth",
+    "ping",
+    "postgres",
+    "redis",
+    "renovate",
+    "selftest",
+    "staticfiles",
+    "status",
+    "templates",
+    "typescript",
+];
@@ -11,21 +11,28 @@ use owo_colors::OwoColorize;
 use prettytable::Table;
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use serde_json::{self, Value};
+use serde_json::{self, json, Value};
 use subprocess::{Exec, ExitStatus, NullFile};
 
+mod config;
+mod dependencies;
+mod errors;
+mod exec;
+mod features;
+mod npmrc;
+mod pin;
+mod presets;
+mod prompt;
 mod render;
+mod report;
 mod settings;
 
-use settings::{Flipper, Settings, When};
+use config::Config;
+use dependencies::{DependencyOverride, DependencySpec, DependencyType, Retirement};
+use errors::Failure;
+use report::{InstallResult, OutputFormat, Report};
+use settings::{Flipper, NodeManager, PackageManager, Settings, When};
 
-// Darn, I had to cap-case NPM. What a shame.
-#[cfg(not(target_os = "windows"))]
-static NPM: &str = "npm";
-#[cfg(target_os = "windows")]
-static NPM: &str = "npm.cmd";
-
-static VOLTA: &str = "volta";
 static NODE_VERSION: &str = "16";
 
 #[derive(Clone, Serialize, Parser)]
@@ -39,18 +46,19 @@ To remove a feature from an existing project, set it to `off`.
 
 Examples:
 boltzmann my-project --redis --website
-boltzmann my-project --githubci=off --honeycomb --jwt"
+boltzmann my-project --githubci=off --honeycomb --jwt
+boltzmann my-project redis hc pg=off"
 )]
 pub struct Flags {
     #[clap(long)]
     /// Enable redis middleware
     redis: Option<Option<Flipper>>,
 
-    #[clap(long)]
+    #[clap(long, alias = "pg")]
     /// Enable postgres middleware
     postgres: Option<Option<Flipper>>,
 
-    #[clap(long)]
+    #[clap(long, alias = "hc")]
     /// Enable tracing via Honeycomb
     honeycomb: Option<Option<Flipper>>,
 
@@ -58,14 +66,46 @@ pub struct Flags {
     /// Enable GitHub actions CI
     githubci: Option<Option<Flipper>>,
 
+    #[clap(long)]
+    /// Add a tag-triggered release workflow to GitHub actions CI
+    githubci_release: Option<Option<Flipper>>,
+
+    #[clap(long)]
+    /// Add a workflow publishing a container image to GitHub actions CI, if there's a Dockerfile
+    githubci_container: Option<Option<Flipper>>,
+
+    #[clap(long)]
+    /// Enable GitLab CI; turns off GitHub actions CI unless that's asked for too
+    gitlabci: Option<Option<Flipper>>,
+
+    #[clap(long, use_value_delimiter = true, value_name = "MAJORS")]
+    /// Comma-separated Node.js versions for the CI test matrix
+    ci_node: Option<Vec<String>>,
+
+    #[clap(long)]
+    /// Configure Dependabot to update everything Boltzmann doesn't manage
+    dependabot: Option<Option<Flipper>>,
+
+    #[clap(long)]
+    /// Configure Renovate to update everything Boltzmann doesn't manage
+    renovate: Option<Option<Flipper>>,
+
+    #[clap(long)]
+    /// Generate a docker-compose.yml for local postgres and redis
+    compose: Option<Option<Flipper>>,
+
     #[clap(long)]
     /// Enable Nunjucks templates
     templates: Option<Option<Flipper>>,
 
-    #[clap(long)]
+    #[clap(long, alias = "ts")]
     /// Scaffold a project implemented in TypeScript
     typescript: Option<Option<Flipper>>,
 
+    #[clap(long)]
+    /// Install type definitions as devDependencies in a TypeScript project built ahead of time
+    dev_typings: Option<Option<Flipper>>,
+
     #[clap(long)]
     /// Enable csrf protection middleware
     csrf: Option<Option<Flipper>>,
@@ -82,6 +122,10 @@ pub struct Flags {
     /// Enable jwt middleware
     jwt: Option<Option<Flipper>>,
 
+    #[clap(long)]
+    /// Generate kubernetes manifests under deploy/k8s
+    kubernetes: Option<Option<Flipper>>,
+
     #[clap(long)]
     /// Enable live reload in development
     livereload: Option<Option<Flipper>>,
@@ -98,9 +142,39 @@ pub struct Flags {
     /// Enable asset bundling via ESBuild
     esbuild: Option<Option<Flipper>>,
 
-    #[structopt(long, help = "Enable node version management via Volta")]
+    #[clap(long, arg_enum)]
+    /// Pin the Node.js version for a version manager
+    node_manager: Option<NodeManager>,
+
+    #[clap(long, hide = true)]
+    /// Deprecated; use --node-manager=volta
     volta: Option<Option<Flipper>>,
 
+    #[clap(long, arg_enum, value_name = "MANAGER")]
+    /// The package manager to install with; detected from the lockfile by default
+    package_manager: Option<PackageManager>,
+
+    #[clap(long, possible_values = settings::NODE_VERSIONS)]
+    /// The major version of Node.js to target; defaults to 16
+    node: Option<String>,
+
+    // package.json metadata; written when the package is created, or when passed explicitly.
+    #[clap(long, value_name = "NAME")]
+    /// The package name; defaults to the name of the destination directory
+    name: Option<String>,
+
+    #[clap(long, value_name = "TEXT")]
+    /// A short description of the service for package.json
+    description: Option<String>,
+
+    #[clap(long, value_name = "AUTHOR")]
+    /// The package author, e.g. "Jane Doe <jane@example.com>"
+    author: Option<String>,
+
+    #[clap(long, value_name = "SPDX")]
+    /// The package license, e.g. MIT or Apache-2.0; common licenses get a LICENSE file
+    license: Option<String>,
+
     // Convenient option groups next. These aren't saved individually.
     #[clap(
         long,
@@ -109,17 +183,53 @@ pub struct Flags {
     /// Enable all features relevant to building websites
     ///
     /// This option group enables the templates, csrf, staticfile, jwt, livereload, ping, and
-    /// status options.
+    /// status options. The same as --preset website.
     website: bool,
 
+    #[clap(long, value_name = "NAME", use_value_delimiter = true)]
+    /// Enable a group of features: api, website, or a preset from your config
+    preset: Vec<String>,
+
     #[clap(long)]
     /// Enable everything (mostly for testing)
     all: bool,
 
+    #[clap(long, value_name = "URL")]
+    /// Install from this npm registry; pass an empty value to go back to the default
+    registry: Option<String>,
+
+    #[clap(long)]
+    /// Install from the package cache when possible
+    prefer_offline: Option<Option<Flipper>>,
+
+    #[clap(long)]
+    /// Install only from the package cache
+    offline: Option<Option<Flipper>>,
+
+    #[clap(long)]
+    /// Ignore peer dependency conflicts when installing with npm; on by default
+    legacy_peer_deps: Option<Option<Flipper>>,
+
+    #[clap(long, value_name = "ARGS", allow_hyphen_values = true)]
+    /// Extra arguments for the install command, e.g. --install-args="--no-audit --no-fund"
+    install_args: Option<String>,
+
+    #[clap(long)]
+    /// Update package.json without installing dependencies
+    no_install: bool,
+
+    #[clap(long, arg_enum, default_value = "text", value_name = "FORMAT")]
+    /// Print human-readable logs, or a JSON report of everything that changed
+    output: OutputFormat,
+
     #[clap(long)]
     /// Update a git-repo destination even if there are changes
     force: bool, // for enemies
 
+    #[clap(long, short)]
+    /// Answer yes to prompts and skip the feature picker, for scripted use
+    yes: bool,
+
     #[clap(
         short,
         long,
@@ -144,14 +254,183 @@ pub struct Flags {
     /// Open the Boltzmann documentation in a web browser
     docs: bool,
 
-    #[clap(
-        parse(from_os_str),
-        default_value = ""
-    )]
-    /// The path to the Boltzmann service
+    #[clap(parse(from_os_str), value_name = "DESTINATION")]
+    /// The path to the Boltzmann service. Other words name features to turn on, or off with
+    /// `=off`: `redis pg=off`
+    positionals: Vec<PathBuf>,
+
+    #[clap(skip)]
     destination: PathBuf,
 }
 
+// Short names for features, as in `boltzmann my-service hc pg=off`.
+const FEATURE_ALIASES: &[(&str, &str)] = &[
+    ("hc", "honeycomb"),
+    ("pg", "postgres"),
+    ("ts", "typescript"),
+];
+
+impl Flags {
+    // The flag for a feature named as `Settings::features()` names it.
+    fn feature_mut(&mut self, feature: &str) -> Option<&mut Option<Option<Flipper>>> {
+        Some(match feature {
+            "compose" => &mut self.compose,
+            "csrf" => &mut self.csrf,
+            "dependabot" => &mut self.dependabot,
+            "esbuild" => &mut self.esbuild,
+            "githubci" => &mut self.githubci,
+            "gitlabci" => &mut self.gitlabci,
+            "honeycomb" => &mut self.honeycomb,
+            "jwt" => &mut self.jwt,
+            "kubernetes" => &mut self.kubernetes,
+            "livereload" => &mut self.livereload,
+            "oauth" => &mut self.oauth,
+            "ping" => &mut self.ping,
+            "postgres" => &mut self.postgres,
+            "redis" => &mut self.redis,
+            "renovate" => &mut self.renovate,
+            "staticfiles" => &mut self.staticfiles,
+            "status" => &mut self.status,
+            "templates" => &mut self.templates,
+            "typescript" => &mut self.typescript,
+            "githubci_release" => &mut self.githubci_release,
+            "githubci_container" => &mut self.githubci_container,
+            "dev_typings" => &mut self.dev_typings,
+            _ => return None,
+        })
+    }
+
+    // Turn a feature on or off from a word like `redis`, `pg=off` or `githubci-release=on`, unless
+    // it's already set and `overwrite` is false. Returns false if the word doesn't name a feature.
+    fn set_feature(&mut self, word: &str, overwrite: bool) -> Result<bool> {
+        let (name, value) = match word.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (word, None),
+        };
+        let feature = FEATURE_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, feature)| *feature)
+            .unwrap_or(name)
+            .replace('-', "_");
+
+        let flag = match self.feature_mut(&feature) {
+            Some(flag) => flag,
+            None => return Ok(false),
+        };
+        // The same values the flags take, so `redis=true` works like `--redis=true`.
+        let value = match value.map(|value| value.parse::<Flipper>()) {
+            None => None,
+            Some(Ok(flipper)) => Some(flipper),
+            Some(Err(_)) => {
+                return Err(Failure::Usage(format!(
+                    "{} takes on or off; got {:?}",
+                    name,
+                    value.unwrap_or_default()
+                ))
+                .into())
+            }
+        };
+        if overwrite || flag.is_none() {
+            *flag = Some(value);
+        }
+        Ok(true)
+    }
+
+    // Sort positional arguments into features and the destination. Anything that names a feature
+    // is one; write `./redis` to scaffold in a directory called redis.
+    fn resolve_positionals(&mut self) -> Result<()> {
+        for arg in std::mem::take(&mut self.positionals) {
+            let word = arg.to_string_lossy().to_string();
+            match &word[..] {
+                "website" => self.website = true,
+                "all" => self.all = true,
+                _ if self.set_feature(&word, true)? => {}
+                _ if word.contains('=') && !word.contains(['/', '\\']) => {
+                    return Err(Failure::Usage(format!(
+                        "there's no feature called {:?}; write ./{} if it's the destination",
+                        word.split('=').next().unwrap_or_default(),
+                        word
+                    ))
+                    .into());
+                }
+                _ if !self.destination.as_os_str().is_empty() => {
+                    return Err(Failure::Usage(format!(
+                        "{:?} isn't a feature, and the destination is already {:?}",
+                        word, self.destination
+                    ))
+                    .into());
+                }
+                _ => self.destination = arg,
+            }
+        }
+        Ok(())
+    }
+
+    // Turn on the features of each preset, including --website. Flags win over presets.
+    fn apply_presets(&mut self, settings: &Settings, config: &Config) -> Result<()> {
+        let names: Vec<String> = self
+            .website
+            .then(|| "website".to_string())
+            .into_iter()
+            .chain(self.preset.iter().cloned())
+            .collect();
+
+        for name in names {
+            let features = presets::lookup(&name, settings, config).ok_or_else(|| {
+                Failure::Usage(format!(
+                    "there's no preset called {:?}; try one of {}",
+                    name,
+                    presets::names(settings, config).join(", ")
+                ))
+            })?;
+            for word in features {
+                if !self.set_feature(&word, false)? {
+                    return Err(Failure::Usage(format!(
+                        "preset {} turns on {:?}, which isn't a feature",
+                        name, word
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn mentions_features(&self) -> bool {
+        self.website
+            || !self.preset.is_empty()
+            || self.all
+            || self.selftest
+            || [
+                &self.compose,
+                &self.csrf,
+                &self.dependabot,
+                &self.esbuild,
+                &self.githubci,
+                &self.githubci_release,
+                &self.githubci_container,
+                &self.gitlabci,
+                &self.honeycomb,
+                &self.jwt,
+                &self.kubernetes,
+                &self.livereload,
+                &self.oauth,
+                &self.ping,
+                &self.postgres,
+                &self.redis,
+                &self.renovate,
+                &self.staticfiles,
+                &self.status,
+                &self.templates,
+                &self.typescript,
+                &self.dev_typings,
+            ]
+            .iter()
+            .any(|flag| flag.is_some())
+    }
+}
+
 #[derive(Deserialize, Clone)]
 struct VersionedScript {
     version: Version,
@@ -170,6 +449,9 @@ struct RunScriptSpec {
 #[derive(Debug, Deserialize, Serialize)]
 struct VoltaSpec {
     node: String,
+
+    #[serde(flatten)]
+    rest: serde_json::Map<String, Value>,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -183,28 +465,144 @@ struct PackageJson {
     #[serde(rename = "devDependencies", skip_serializing_if = "Option::is_none")]
     dev_dependencies: Option<serde_json::Map<String, Value>>,
 
+    #[serde(rename = "peerDependencies", skip_serializing_if = "Option::is_none")]
+    peer_dependencies: Option<serde_json::Map<String, Value>>,
+
+    #[serde(rename = "optionalDependencies", skip_serializing_if = "Option::is_none")]
+    optional_dependencies: Option<serde_json::Map<String, Value>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overrides: Option<serde_json::Map<String, Value>>,
+
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     module_type: Option<String>,
 
     scripts: Option<serde_json::Map<String, Value>>,
     boltzmann: Option<Settings>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    engines: Option<serde_json::Map<String, Value>>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     volta: Option<Option<VoltaSpec>>,
 }
 
-fn load_package_json(flags: &Flags, default_settings: Settings) -> Option<PackageJson> {
+impl PackageJson {
+    // Everything that changes what an install would put in node_modules.
+    fn install_inputs(&self) -> Vec<Value> {
+        let maps = [
+            &self.dependencies,
+            &self.dev_dependencies,
+            &self.peer_dependencies,
+            &self.optional_dependencies,
+            &self.overrides,
+        ]
+        .into_iter()
+        .map(|deps| Value::Object(deps.clone().unwrap_or_default()));
+        let fields = ["pnpm", "resolutions"]
+            .into_iter()
+            .map(|field| self.rest.get(field).cloned().unwrap_or(Value::Null));
+        maps.chain(fields).collect()
+    }
+
+    fn direct_range(&self, name: &str) -> Option<&Value> {
+        [&self.dependencies, &self.dev_dependencies]
+            .into_iter()
+            .find_map(|deps| deps.as_ref()?.get(name))
+    }
+
+    // Take the overrides from wherever `manager` reads them: `overrides` for npm, `pnpm.overrides`
+    // for pnpm and `resolutions` for yarn. They come back the way npm writes them.
+    fn take_overrides(&mut self, manager: PackageManager) -> serde_json::Map<String, Value> {
+        let overrides = match manager {
+            PackageManager::Npm => return self.overrides.take().unwrap_or_default(),
+            PackageManager::Pnpm => {
+                match self.rest.get_mut("pnpm").and_then(Value::as_object_mut) {
+                    Some(pnpm) if pnpm.get("overrides").is_some_and(Value::is_object) => {
+                        let overrides = pnpm.remove("overrides");
+                        if pnpm.is_empty() {
+                            self.rest.remove("pnpm");
+                        }
+                        overrides
+                    }
+                    _ => None,
+                }
+            }
+            PackageManager::Yarn => match self.rest.get("resolutions") {
+                Some(Value::Object(_)) => self.rest.remove("resolutions"),
+                _ => None,
+            },
+        };
+
+        // Neither can refer to a direct dependency's range as npm's `$name` does, so we write the
+        // range itself; turn it back into a reference, so a later upgrade follows it.
+        let mut overrides = match overrides {
+            Some(Value::Object(overrides)) => overrides,
+            _ => return Default::default(),
+        };
+        for (name, range) in overrides.iter_mut() {
+            if self.direct_range(name) == Some(range) {
+                *range = Value::String(format!("${}", name));
+            }
+        }
+        overrides
+    }
+
+    // The reverse of `take_overrides`; no overrides removes the field.
+    fn set_overrides(&mut self, manager: PackageManager, mut overrides: serde_json::Map<String, Value>) {
+        if manager != PackageManager::Npm {
+            for (name, range) in overrides.iter_mut() {
+                if range.as_str() == Some(&format!("${}", name)) {
+                    if let Some(direct) = self.direct_range(name) {
+                        *range = direct.clone();
+                    }
+                }
+            }
+        }
+        if overrides.is_empty() {
+            return;
+        }
+
+        match manager {
+            PackageManager::Npm => {
+                self.overrides.replace(overrides);
+            }
+            PackageManager::Pnpm => {
+                let pnpm = self
+                    .rest
+                    .entry("pnpm")
+                    .or_insert_with(|| Value::Object(Default::default()));
+                if let Value::Object(pnpm) = pnpm {
+                    pnpm.insert("overrides".to_string(), Value::Object(overrides));
+                }
+            }
+            PackageManager::Yarn => {
+                self.rest.insert("resolutions".to_string(), Value::Object(overrides));
+            }
+        }
+    }
+}
+
+// Ok(None) if there's no package.json yet. One we can't read is an error: we'd overwrite it.
+fn load_package_json(flags: &Flags, default_settings: Settings) -> Result<Option<PackageJson>> {
     let mut path = flags.destination.clone();
     path.push("package.json");
 
-    let mut fd = std::fs::File::open(&path).ok()?;
+    let mut fd = match std::fs::File::open(&path) {
+        Ok(fd) => fd,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).context(Failure::InvalidPackageJson(path)),
+    };
 
     let mut contents = Vec::new();
-    fd.read_to_end(&mut contents).ok()?;
+    if let Err(err) = fd.read_to_end(&mut contents) {
+        return Err(err).context(Failure::InvalidPackageJson(path));
+    }
 
-    let mut package_json = serde_json::from_slice::<PackageJson>(&contents[..]).ok()?;
+    let mut package_json = serde_json::from_slice::<PackageJson>(&contents[..])
+        .context(Failure::InvalidPackageJson(path))?;
     package_json.boltzmann = package_json.boltzmann.or(Some(default_settings));
-    Some(package_json)
+    Ok(Some(package_json))
 }
 
 // Return ok if we can proceed, and an error saying why if we can't.
@@ -227,36 +625,144 @@ fn check_git_status(flags: &Flags) -> Result<()> {
     match exit_status {
         ExitStatus::Exited(129) => Ok(()), // target is not a git dir; this is fine
         ExitStatus::Exited(0) => Ok(()),   // target is clean
-        ExitStatus::Exited(1) => Err(anyhow!(
-            "git working directory is dirty; pass --force if you want to run anyway"
-        )),
+        ExitStatus::Exited(1) => Err(Failure::DirtyTree.into()),
         // all other exit codes are are fine
         _ => Ok(()),
     }
 }
 
-fn initialize_package_json(path: &Path, verbosity: u64) -> Result<()> {
-    if let Err(e) = std::fs::DirBuilder::new().create(&path) {
-        if e.kind() != std::io::ErrorKind::AlreadyExists {
-            return Err(e.into());
-        }
+fn initialize_package_json(path: &Path, default_settings: Settings) -> Result<PackageJson> {
+    std::fs::create_dir_all(path)?;
+
+    // The fields `npm init --yes` would have given us, minus the placeholders.
+    let mut rest = serde_json::Map::new();
+    rest.insert("name".to_string(), package_name(path).into());
+    rest.insert("version".to_string(), "1.0.0".into());
+    rest.insert("description".to_string(), "".into());
+    rest.insert("author".to_string(), "".into());
+    rest.insert("license".to_string(), "ISC".into());
+
+    Ok(PackageJson {
+        rest,
+        scripts: Some(Default::default()),
+        boltzmann: Some(default_settings),
+        ..Default::default()
+    })
+}
+
+// npm package names are lowercase and URL-safe; make one from the directory name.
+fn package_name(path: &Path) -> String {
+    let dirname = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name: String = dirname
+        .chars()
+        .map(|ch| match ch {
+            'a'..='z' | '0'..='9' | '-' | '.' | '_' | '~' => ch,
+            _ => '-',
+        })
+        .collect();
+    let name = name.trim_start_matches(['.', '_']);
+
+    if name.is_empty() {
+        "boltzmann-app".to_string()
+    } else {
+        name.to_string()
     }
+}
+
+// We don't write Dockerfiles, but the conventional one installs with `npm ci` and builds with
+// `npm run`. Swap those for the new package manager's commands, leaving everything else alone.
+fn update_dockerfile(path: &Path, from: PackageManager, to: PackageManager) -> Result<bool> {
+    let dockerfile = path.join("Dockerfile");
+    let contents = match std::fs::read_to_string(&dockerfile) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(false),
+    };
 
-    let mut subproc = Exec::cmd(NPM).arg("init").arg("--yes").cwd(&path);
+    let mut updated = contents
+        .replace(&from.ci_install(), &to.ci_install())
+        .replace(&format!("{} run ", from), &format!("{} run ", to))
+        .replace(from.lockfile(), to.lockfile());
+
+    // Node ships pnpm and yarn behind corepack, which is off by default.
+    if to != PackageManager::Npm && !updated.contains("corepack enable") {
+        updated = updated.replacen(
+            &format!("RUN {}", to.ci_install()),
+            &format!("RUN corepack enable && {}", to.ci_install()),
+            1,
+        );
+    }
 
-    subproc = if verbosity < 3 {
-        // only noisy for trace
-        subproc.stdout(NullFile).stderr(NullFile)
-    } else {
-        subproc
+    if updated == contents {
+        return Ok(false);
+    }
+
+    std::fs::write(&dockerfile, updated)
+        .with_context(|| format!("Failed to update {:?}", dockerfile))?;
+    Ok(true)
+}
+
+// tsconfig.json is only written on the first scaffold, but the `@tsconfig/nodeXX` base it extends
+// has to follow the targeted Node.js version or `tsc` can't find it.
+fn update_tsconfig_base(path: &Path, node_version: &str) -> Result<bool> {
+    let tsconfig = path.join("tsconfig.json");
+    let contents = match std::fs::read_to_string(&tsconfig) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(false),
     };
 
-    let exit_status = subproc.join()?;
+    let needle = "\"@tsconfig/node";
+    let start = match contents.find(needle) {
+        Some(idx) => idx + needle.len(),
+        None => return Ok(false),
+    };
+    let digits = contents[start..]
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(contents.len() - start);
 
-    match exit_status {
-        ExitStatus::Exited(0) => Ok(()),
-        _ => Err(anyhow!("npm init exited with non-zero status")),
+    if &contents[start..start + digits] == node_version {
+        return Ok(false);
+    }
+
+    let updated = format!(
+        "{}{}{}",
+        &contents[..start],
+        node_version,
+        &contents[start + digits..]
+    );
+    std::fs::write(&tsconfig, updated).with_context(|| format!("Failed to update {:?}", tsconfig))?;
+    info!("    tsconfig.json now extends @tsconfig/node{}", node_version);
+    Ok(true)
+}
+
+// Remove dependencies matching `pattern` (a name containing `{node_version}`) that were installed
+// for some Node.js version other than the one we're targeting now, returning their names.
+fn remove_node_variants(
+    target: &mut serde_json::Map<String, Value>,
+    pattern: &str,
+    keep: &str,
+) -> Vec<String> {
+    let (prefix, suffix) = pattern.split_once("{node_version}").unwrap_or((pattern, ""));
+    let stale: Vec<String> = target
+        .keys()
+        .filter(|name| {
+            name.as_str() != keep
+                && name.len() > prefix.len() + suffix.len()
+                && name.starts_with(prefix)
+                && name.ends_with(suffix)
+                && name[prefix.len()..name.len() - suffix.len()]
+                    .chars()
+                    .all(|ch| ch.is_ascii_digit())
+        })
+        .cloned()
+        .collect();
+
+    for name in &stale {
+        target.remove(name);
     }
+    stale
 }
 
 fn print_table<T: std::fmt::Display + Clone>(mut input: Vec<T>, columns: usize, indent: usize) {
@@ -273,34 +779,19 @@ fn print_table<T: std::fmt::Display + Clone>(mut input: Vec<T>, columns: usize,
     table.printstd();
 }
 
-// data structures for dep lists
-#[derive(Deserialize)]
-enum DependencyType {
-    Normal,
-    Development,
-}
-
-impl ::std::fmt::Display for DependencyType {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        match *self {
-            DependencyType::Normal => f.write_str(""),
-            DependencyType::Development => f.write_str("(dev)"),
-        }
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {:?}", err);
+        std::process::exit(errors::exit_code(&err));
     }
 }
 
-#[derive(Deserialize)]
-struct DependencySpec {
-    name: String,
-    version: String,
-    kind: DependencyType,
-    preconditions: Option<When>,
-}
-
-fn main() -> anyhow::Result<(), anyhow::Error> {
+fn run() -> Result<()> {
     let mut flags = Flags::parse();
+    flags.resolve_positionals()?;
 
-    let verbosity: u64 = if flags.silent || flags.quiet {
+    // JSON output replaces the logs entirely.
+    let verbosity: u64 = if flags.silent || flags.quiet || flags.output == OutputFormat::Json {
         0
     } else {
         flags.verbose + 1
@@ -318,6 +809,10 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
         .unwrap_or_else(|| "0.0.0")
         .to_string();
     let semver_version = Version::parse(&version).unwrap_or_else(|_| Version::new(0, 0, 0));
+    let mut report = Report {
+        version: version.clone(),
+        ..Default::default()
+    };
 
     if flags.docs {
         let subproc = match std::env::consts::OS {
@@ -337,23 +832,12 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
     }
 
     // Is this a tty? What is the user trying to do? Is there a user? What is an electron anyway?
-    if flags.destination.as_os_str().is_empty() && atty::is(Stream::Stdout) {
+    if flags.destination.as_os_str().is_empty() && !flags.yes && atty::is(Stream::Stdout) {
         warn!("Scaffolding a Boltzmann service in the current working directory.");
         info!("To see full help, run `boltzmann --help`.");
-        print!("Scaffold here? (y/n): ");
-        std::io::stdout().flush()?;
-        let mut buffer = String::new();
-        std::io::stdin().read_line(&mut buffer)?;
-        buffer.make_ascii_uppercase();
-        match &buffer[..] {
-            "Y\r\n" => {}
-            "YES\r\n" => {}
-            "Y\n" => {}
-            "YES\n" => {}
-            _ => {
-                warn!("Exiting without scaffolding.");
-                ::std::process::exit(0);
-            }
+        if !prompt::confirm("Scaffold here?")? {
+            warn!("Exiting without scaffolding.");
+            ::std::process::exit(0);
         }
     }
 
@@ -366,19 +850,20 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
     let mut first_scaffold = false;
     let mut prev_version: Version = Version::new(0, 0, 0);
 
+    let config = config::load()?;
     info!(
         "Scaffolding a Boltzmann service in {}",
         flags.destination.to_str().unwrap().bold().blue()
     );
-    let default_settings = Settings {
+    let default_settings = config.defaults_over(Settings {
         githubci: Some(true),
         status: Some(true),
         ping: Some(true),
         ..Default::default()
-    };
+    })?;
 
     let mut package_json = if let Some(mut package_json) =
-        load_package_json(&flags, default_settings.clone())
+        load_package_json(&flags, default_settings.clone())?
     {
         if let Some(t) = package_json.boltzmann.clone() {
             prev_version = Version::parse(&t.version.unwrap_or_else(|| "0.0.0".to_string()))
@@ -392,30 +877,148 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
         } else {
             info!("    loaded settings from existing package.json");
         }
-        package_json.scripts = package_json.scripts.or_else(Default::default);
+        package_json.scripts = Some(package_json.scripts.unwrap_or_default());
+        report.previous_version = Some(prev_version.to_string());
         package_json
     } else {
         first_scaffold = true;
         info!("    initializing a new NPM package...");
-        initialize_package_json(&flags.destination, verbosity)
-            .with_context(|| format!("Failed to run `npm init -y` in {:?}", flags.destination))?;
-        let mut package_json = load_package_json(&flags, default_settings).unwrap();
-        package_json.scripts.replace(Default::default());
-        package_json
+        initialize_package_json(&flags.destination, default_settings)
+            .with_context(|| format!("Failed to create {:?}", flags.destination))?
     };
 
+    for (key, value) in [
+        ("name", &flags.name),
+        ("description", &flags.description),
+        ("author", &flags.author),
+        ("license", &flags.license),
+    ] {
+        if let Some(value) = value {
+            package_json.rest.insert(key.to_string(), value.clone().into());
+        }
+    }
+
     if package_json.boltzmann.is_none() {
         return Err(anyhow!("Somehow we do not have default settings! Please file a bug."));
     }
 
     let settings = package_json.boltzmann.take().unwrap();
-    let updated_settings = settings.merge_flags(version.clone(), NODE_VERSION.to_string(), &flags);
+    flags.apply_presets(&settings, &config)?;
+
+    // Run bare in a terminal, offer a checklist instead of making people look up flags.
+    if !flags.yes
+        && verbosity > 0
+        && !flags.mentions_features()
+        && atty::is(Stream::Stdin)
+        && atty::is(Stream::Stdout)
+        && !prompt::pick_features(&settings, &mut flags)?
+    {
+        warn!("Exiting without scaffolding.");
+        ::std::process::exit(0);
+    }
+    let mut updated_settings = settings.merge_flags(version.clone(), NODE_VERSION.to_string(), &flags);
+    updated_settings.name = package_json
+        .rest
+        .get("name")
+        .and_then(|name| name.as_str())
+        .map(String::from);
+
+    // Until a manager is saved, the lockfile says which one the project has been using.
+    let detected_package_manager = PackageManager::detect(&flags.destination);
+    let previous_package_manager = settings
+        .package_manager
+        .or(detected_package_manager)
+        .unwrap_or(PackageManager::Npm);
+    if updated_settings.package_manager.is_none() {
+        // The user's default is for projects new to Boltzmann, and loses to an existing lockfile.
+        let preferred = config
+            .defaults
+            .package_manager
+            .filter(|_| settings.version.is_none());
+        updated_settings.package_manager = Some(
+            detected_package_manager
+                .or(preferred)
+                .unwrap_or(PackageManager::Npm),
+        );
+    }
+    let package_manager = updated_settings.package_manager();
+
+    let node_version = updated_settings
+        .node_version
+        .clone()
+        .unwrap_or_else(|| NODE_VERSION.to_string());
+    if !settings::NODE_VERSIONS.contains(&&node_version[..]) {
+        return Err(Failure::Usage(format!(
+            "Node.js {} is not supported; pass --node with one of {}",
+            node_version,
+            settings::NODE_VERSIONS.join(", ")
+        ))
+        .into());
+    }
+
+    if let Some(versions) = &updated_settings.ci_node_versions {
+        if let Some(bad) = versions
+            .iter()
+            .find(|version| version.is_empty() || !version.chars().all(|ch| ch.is_ascii_digit()))
+        {
+            return Err(Failure::Usage(format!(
+                "--ci-node takes Node.js major versions, like 16,18; got {:?}",
+                bad
+            ))
+            .into());
+        }
+    }
+
+    render::scaffold(&mut target, &updated_settings, &mut report)
+        .context(Failure::Render)?;
+
+    if let Some(true) = updated_settings.typescript {
+        if update_tsconfig_base(&target, &node_version)? {
+            report.file("tsconfig.json", "updated");
+        }
+    }
+
+    if let Some(license) = &flags.license {
+        // The author field may be a string or a {name, email} object.
+        let holder = match package_json.rest.get("author") {
+            Some(Value::String(author)) if !author.is_empty() => author.clone(),
+            Some(Value::Object(author)) => author
+                .get("name")
+                .and_then(|name| name.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            _ => updated_settings.name.clone().unwrap_or_default(),
+        };
+        if !render::license(&target, license, &holder, &mut report)? {
+            warn!(
+                "    no LICENSE text for {}; add one by hand",
+                license.bold().blue()
+            );
+        }
+    }
+
+    // Projects that started with npm have it baked into their Dockerfile, too.
+    if package_manager != previous_package_manager
+        && update_dockerfile(&target, previous_package_manager, package_manager)?
+    {
+        info!("    updated Dockerfile for {}", package_manager.bold().blue());
+        report.file("Dockerfile", "updated");
+    }
 
-    render::scaffold(&mut target, &updated_settings).context("Failed to render Boltzmann files")?;
+    if updated_settings.githubci.unwrap_or(false)
+        && updated_settings.githubci_container.unwrap_or(false)
+        && !target.join("Dockerfile").exists()
+    {
+        warn!("    no Dockerfile found; skipping the container image workflow");
+    }
 
-    let old = serde_json::to_value(settings)?;
+    let old = serde_json::to_value(&settings)?;
     let new = serde_json::to_value(&updated_settings)?;
 
+    let install_inputs = package_json.install_inputs();
+
+    let mut overrides = package_json.take_overrides(previous_package_manager);
+
     let mut dependencies = package_json
         .dependencies
         .take()
@@ -424,7 +1027,15 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
         .dev_dependencies
         .take()
         .unwrap_or_default();
-    let candidates: Vec<DependencySpec> = ron::de::from_str(include_str!("dependencies.ron"))?;
+    let mut peerdeps = package_json
+        .peer_dependencies
+        .take()
+        .unwrap_or_default();
+    let mut optionaldeps = package_json
+        .optional_dependencies
+        .take()
+        .unwrap_or_default();
+    let candidates: Vec<DependencySpec> = dependencies::candidates()?;
 
     let mut table = Table::new();
     table.set_format(*prettytable::format::consts::FORMAT_CLEAN);
@@ -432,13 +1043,137 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
     let mut actions: Vec<String> = Vec::new();
     let false_sentinel = Value::Bool(false);
 
-    for candidate in candidates {
-        let target = match candidate.kind {
-            DependencyType::Normal => &mut dependencies,
+    // JavaScript projects only need type definitions for editor support, and projects compiled
+    // ahead of time only need them to build.
+    let typings_are_dev = !updated_settings.typescript.unwrap_or(false)
+        || updated_settings.dev_typings.unwrap_or(false);
+
+    for mut candidate in candidates {
+        let name = candidate.name.replace("{node_version}", &node_version);
+        let kind = candidate.kind.resolve(typings_are_dev);
+        let wants_feature = candidate
+            .preconditions
+            .as_ref()
+            .map(|preconditions| preconditions.are_satisfied_by(&new))
+            .unwrap_or(true);
+        let opted_out = matches!(
+            updated_settings.dependency_override(&name),
+            Some(DependencyOverride::Managed(false))
+        );
+
+        // Only move typings we're managing: not opted-out ones, or ones the user added themselves.
+        if let (DependencyType::Types, true, false) = (candidate.kind, wants_feature, opted_out) {
+            let (from, to, destination) = if typings_are_dev {
+                (&mut dependencies, &mut devdeps, "devDependencies")
+            } else {
+                (&mut devdeps, &mut dependencies, "dependencies")
+            };
+            if let Some(range) = from.remove(&name[..]) {
+                actions.push(format!(
+                    "{} moved to {}",
+                    name.bold().magenta(),
+                    destination
+                ));
+                report.dependency(&name, destination, "moved", range.as_str(), range.as_str());
+                to.insert(name.clone(), range);
+            }
+        }
+
+        let target = match kind {
+            DependencyType::Normal | DependencyType::Types => &mut dependencies,
             DependencyType::Development => &mut devdeps,
+            DependencyType::Peer => &mut peerdeps,
+            DependencyType::Optional => &mut optionaldeps,
+            DependencyType::Override => &mut overrides,
         };
 
-        let has_dep_currently = target.contains_key(&candidate.name[..]);
+        if name != candidate.name {
+            for stale in remove_node_variants(target, &candidate.name, &name) {
+                actions.push(format!(
+                    "ⅹ {} (node {} selected)",
+                    stale.strikethrough().magenta(),
+                    node_version
+                ));
+                report.dependency(&stale, kind.field(), "removed", None, None);
+            }
+        }
+
+        let current_range = target
+            .get(&name[..])
+            .and_then(|current| current.as_str())
+            .map(String::from);
+
+        if let Some(retired) = &candidate.retired {
+            if let Some(current) = &current_range {
+                let overridden = updated_settings.dependency_override(&name).is_some();
+                let scaffolded_by = settings.version.as_ref().map(|_| &prev_version);
+                match candidate.retirement(scaffolded_by, current, overridden) {
+                    Retirement::Remove => {
+                        actions.push(format!(
+                            "ⅹ {} (retired in {})",
+                            name.strikethrough().magenta(),
+                            retired
+                        ));
+                        report.dependency(&name, kind.field(), "removed", Some(current), None);
+                        target.remove(&name[..]);
+                    }
+                    Retirement::LeaveInPlace => {
+                        actions.push(format!("{} left in place", name.bold().magenta()));
+                        report.dependency(&name, kind.field(), "kept", Some(current), Some(current));
+                    }
+                    Retirement::Ignore => {}
+                }
+            }
+            continue;
+        }
+
+        match updated_settings.dependency_override(&name) {
+            Some(DependencyOverride::Managed(false)) => {
+                let would_change = match &current_range {
+                    Some(current) => !wants_feature || current != &candidate.version,
+                    None => wants_feature,
+                };
+                if would_change {
+                    actions.push(format!("{} kept by override", name.bold().magenta()));
+                    let current = current_range.as_deref();
+                    report.dependency(&name, kind.field(), "kept", current, current);
+                }
+                continue;
+            }
+            Some(DependencyOverride::Range(range)) => {
+                // Say so when the override changes the range; a new entry is announced below.
+                if let (Some(current), true) = (&current_range, wants_feature) {
+                    if current != range {
+                        actions.push(format!(
+                            "{}@{} set by override",
+                            name.bold().magenta(),
+                            range
+                        ));
+                    }
+                }
+                candidate.version = range.clone();
+            }
+            _ => {
+                // Don't undo an upgrade: keep a range that's already at or past ours. Only for
+                // features that are on; otherwise the package isn't ours to keep.
+                if let (Some(current), true) = (&current_range, wants_feature) {
+                    if current != &candidate.version
+                        && dependencies::satisfies_spec(current, &candidate.version)
+                    {
+                        actions.push(format!(
+                            "{}@{} kept; satisfies {}",
+                            name.bold().magenta(),
+                            current,
+                            candidate.version
+                        ));
+                        report.dependency(&name, kind.field(), "kept", Some(current), Some(current));
+                        candidate.version = current.clone();
+                    }
+                }
+            }
+        }
+
+        let has_dep_currently = target.contains_key(&name[..]);
 
         if let Some(preconditions) = candidate.preconditions {
             let wants_feature = preconditions.are_satisfied_by(&new);
@@ -449,50 +1184,64 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
                 if !has_dep_currently {
                     let why = if !preconditions.all_of.is_empty() {
                         preconditions.all_of.join(", ")
+                    } else if !preconditions.any_of.is_empty() {
+                        preconditions.any_of.join(" or ")
                     } else {
                         "prereqs".to_string()
                     };
                     actions.push(format!(
                         "{}@{} ({} enabled)",
-                        candidate.name.bold().magenta(),
+                        name.bold().magenta(),
                         candidate.version,
                         why
                     ));
                 }
-                target.insert(candidate.name, candidate.version.into());
+                if current_range.as_ref() != Some(&candidate.version) {
+                    let action = if has_dep_currently { "updated" } else { "added" };
+                    let from = current_range.as_deref();
+                    report.dependency(&name, kind.field(), action, from, Some(&candidate.version));
+                }
+                target.insert(name, candidate.version.into());
             } else if wants_feature != used_to_have {
                 if has_dep_currently {
                     let why = if !preconditions.all_of.is_empty() {
                         preconditions.all_of.join(", ")
+                    } else if !preconditions.any_of.is_empty() {
+                        preconditions.any_of.join(" or ")
                     } else {
                         "prereqs".to_string()
                     };
                     actions.push(format!(
                         "ⅹ {} ({} disabled)",
-                        candidate.name.strikethrough().magenta(),
+                        name.strikethrough().magenta(),
                         why
                     ));
+                    let from = current_range.as_deref();
+                    report.dependency(&name, kind.field(), "removed", from, None);
                 }
-                target.remove(&candidate.name[..]);
+                target.remove(&name[..]);
             }
         } else if !has_dep_currently {
             actions.push(format!(
                 "{}@{} {}",
-                candidate.name.bold().magenta(),
+                name.bold().magenta(),
                 candidate.version,
-                candidate.kind
+                kind
             ));
-            target.insert(candidate.name, candidate.version.into());
-        } else if let Some(current_value) = target.get(&candidate.name[..]) {
+            report.dependency(&name, kind.field(), "added", None, Some(&candidate.version));
+            target.insert(name, candidate.version.into());
+        } else if let Some(current_value) = target.get(&name[..]) {
             if current_value.as_str().unwrap_or("") != candidate.version.as_str() {
                 actions.push(format!(
                     "{}@{} ➜ {} {}",
-                    candidate.name.bold().magenta(),
+                    name.bold().magenta(),
                     current_value,
                     candidate.version,
-                    candidate.kind
+                    kind
                 ));
-                target.insert(candidate.name, candidate.version.into());
+                let from = current_value.as_str();
+                report.dependency(&name, kind.field(), "updated", from, Some(&candidate.version));
+                target.insert(name, candidate.version.into());
             }
         }
     }
@@ -510,13 +1259,100 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
 
     package_json.dependencies.replace(dependencies);
     package_json.dev_dependencies.replace(devdeps);
+    // Leave these keys out entirely for projects that have nothing in them.
+    package_json.peer_dependencies = Some(peerdeps).filter(|deps| !deps.is_empty());
+    package_json.optional_dependencies = Some(optionaldeps).filter(|deps| !deps.is_empty());
+    package_json.set_overrides(package_manager, overrides);
+
+    // Keep engines.node in step with the targeted version, unless someone has tuned it by hand.
+    let mut engines = package_json.engines.take().unwrap_or_default();
+    let managed_engine = |version: &str| format!(">={}", version);
+    let previous_engine = settings
+        .node_version
+        .as_deref()
+        .map(managed_engine)
+        .unwrap_or_default();
+    match engines.get("node").and_then(|current| current.as_str()) {
+        Some(current) if current != previous_engine => {}
+        _ => {
+            engines.insert("node".to_string(), managed_engine(&node_version).into());
+        }
+    }
+    package_json.engines.replace(engines);
+
     package_json.boltzmann.replace(updated_settings.clone());
 
-    match updated_settings.volta {
-        Some(true) => (),
-        _ => {
-            package_json.volta = None;
-        },
+    // Pin the Node.js version for whichever version manager is in use, clearing out the old
+    // manager's pin if that changed. Volta keeps its pin in package.json; the rest use files.
+    let previous_manager = settings.node_manager();
+    let node_manager = updated_settings.node_manager();
+    let mut pin_actions: Vec<String> = Vec::new();
+
+    if previous_manager != node_manager {
+        let removed = if previous_manager == NodeManager::Volta {
+            package_json.volta.take().is_some()
+        } else {
+            pin::unpin(&target, previous_manager)?
+        };
+        if removed {
+            pin_actions.push(format!("removed {} pin", previous_manager));
+            if let Some(name) = pin::pin_file_name(previous_manager) {
+                // asdf's file may survive with other tools' versions in it.
+                let action = if target.join(name).exists() { "updated" } else { "removed" };
+                report.file(name, action);
+            }
+        }
+    }
+
+    if node_manager == NodeManager::Volta {
+        let (existing, rest) = match package_json.volta.take().flatten() {
+            Some(spec) => (Some(spec.node), spec.rest),
+            None => (None, Default::default()),
+        };
+        let node = pin::exact_version(existing.as_deref(), &node_version);
+        if existing.as_deref() != Some(&node[..]) {
+            pin_actions.push(format!("volta pinned to node@{}", node));
+        }
+        package_json.volta.replace(Some(VoltaSpec { node, rest }));
+    } else if pin::pin(&target, node_manager, &node_version)? {
+        pin_actions.push(format!("{} pinned to node@{}", node_manager, node_version));
+        if let Some(name) = pin::pin_file_name(node_manager) {
+            report.file(name, "updated");
+        }
+    }
+
+    for action in pin_actions {
+        info!("    {}", action);
+    }
+
+    // Mirror our install options in .npmrc, removing the ones that were turned off. That includes
+    // the default legacy-peer-deps, so a manual `npm install` or CI's `npm ci` resolves like ours.
+    let enabled = |on: Option<bool>| on.filter(|on| *on).map(|_| "true".to_string());
+    let legacy_peer_deps = |manager: PackageManager, settings: &Settings| {
+        enabled(Some(manager == PackageManager::Npm && settings.legacy_peer_deps()))
+    };
+    let npmrc_entries: Vec<(&str, Option<String>)> = [
+        ("registry", settings.registry.clone(), updated_settings.registry.clone()),
+        (
+            "prefer-offline",
+            enabled(settings.prefer_offline),
+            enabled(updated_settings.prefer_offline),
+        ),
+        ("offline", enabled(settings.offline), enabled(updated_settings.offline)),
+        (
+            "legacy-peer-deps",
+            legacy_peer_deps(previous_package_manager, &settings),
+            legacy_peer_deps(package_manager, &updated_settings),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, before, after)| before.is_some() || after.is_some())
+    .map(|(key, _, after)| (key, after))
+    .collect();
+    if npmrc::update(&target, &npmrc_entries)? {
+        info!("    updated .npmrc");
+        let action = if target.join(".npmrc").exists() { "updated" } else { "removed" };
+        report.file(".npmrc", action);
     }
 
     // Update package.json run scripts.
@@ -528,7 +1364,9 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
     let candidates: Vec<RunScriptSpec> = ron::de::from_str(include_str!("runscripts.ron"))?;
     let mut scripts = package_json.scripts.take().unwrap();
 
-    'next: for candidate in candidates {
+    'next: for mut candidate in candidates {
+        candidate.value = package_manager.run_script(&candidate.value);
+
         if let Some(preconditions) = candidate.preconditions {
             let wants_feature = preconditions.all_of.iter().all(|feature| {
                 let has_feature = new.get(feature).unwrap_or(&false_sentinel);
@@ -556,7 +1394,7 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
                     if candidate.versions.is_empty() {
                         debug!(
                             "{} has no history",
-                            format!("npm run {}", candidate.key).bold().red()
+                            format!("{} run {}", package_manager, candidate.key).bold().red()
                         );
                         continue 'next;
                     }
@@ -575,12 +1413,18 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
                                 .unwrap_or(&false_sentinel)
                                 .as_str()
                                 .unwrap_or("");
-                            if !current.to_string().is_empty() && current != potential_source.value
+                            // ...as written for the package manager in use back then.
+                            let previous_value =
+                                previous_package_manager.run_script(&potential_source.value);
+                            if !current.is_empty()
+                                && current != previous_value
+                                && current != candidate.value
                             {
                                 actions.push(format!(
                                     "{} left in place",
-                                    format!("npm run {}", candidate.key).bold().red()
+                                    format!("{} run {}", package_manager, candidate.key).bold().red()
                                 ));
+                                report.script(&candidate.key, "kept", Some(current));
                                 continue 'next;
                             }
                             break;
@@ -599,8 +1443,9 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
         {
             actions.push(format!(
                 "{} set",
-                format!("npm run {}", candidate.key).bold().green()
+                format!("{} run {}", package_manager, candidate.key).bold().green()
             ));
+            report.script(&candidate.key, "set", Some(&candidate.value));
             scripts.insert(candidate.key, serde_json::Value::String(candidate.value));
         }
     }
@@ -611,58 +1456,147 @@ fn main() -> anyhow::Result<(), anyhow::Error> {
         print_table(actions, 3, 6);
     }
 
-    info!("    writing updated package.json...");
     target.push("package.json");
-    let mut fd = std::fs::OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open(&target)
-        .with_context(|| format!("Failed to update {:?}", target))?;
-    serde_json::to_writer_pretty(&mut fd, &package_json)?;
+    // npm ends the files it writes with a newline; match it so an install doesn't look like a change.
+    let mut contents = serde_json::to_vec_pretty(&package_json)?;
+    contents.push(b'\n');
+    let action = match std::fs::read(&target) {
+        Ok(existing) if existing == contents => "unchanged",
+        Ok(_) => "updated",
+        Err(_) => "created",
+    };
+    report.file("package.json", action);
+    // Leave an unchanged file alone, so editors and watchers don't see a change.
+    if action != "unchanged" {
+        info!("    writing updated package.json...");
+        let mut fd = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&target)
+            .with_context(|| format!("Failed to update {:?}", target))?;
+        fd.write_all(&contents)?;
+    }
     target.pop();
 
-    if let Some(true) = updated_settings.volta {
-        let mut subproc = Exec::cmd(VOLTA).arg("pin").arg(format!("node@{}", NODE_VERSION)).cwd(&target);
-
-        subproc = if verbosity < 2 {
-            subproc.stdout(NullFile).stderr(NullFile)
-        } else {
-            subproc
-        };
-
-        info!("    running volta pin...");
-        let exit_status = subproc.join()?;
-
-        match exit_status {
-            ExitStatus::Exited(0) => {},
-            _ => {
-                return Err(anyhow!("volta pin exited with non-zero status").into());
+    let unchanged = install_inputs == package_json.install_inputs()
+        && package_manager == previous_package_manager;
+    let installing = json!({
+        "packageManager": package_manager,
+        "dependencies": package_json.install_inputs(),
+    });
+    let record = target.join(INSTALL_RECORD);
+    let last_installed = std::fs::read(&record)
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<Value>(&contents).ok());
+    let step = install_step(
+        &installing,
+        last_installed.as_ref(),
+        unchanged,
+        target.join(package_manager.lockfile()).exists(),
+    );
+    let install_args: &[&str] = if flags.no_install {
+        info!(
+            "    skipping install; run `{} install` when you're ready",
+            package_manager
+        );
+        &[]
+    } else {
+        match step {
+            InstallStep::Skip => {
+                info!("    dependencies unchanged; skipping install");
+                &[]
             }
+            InstallStep::Ci => package_manager.ci_install_args(),
+            InstallStep::Install => &["install"],
         }
     };
 
-    // It's not actually possible, afaict, to install the opentelemetry libraries
-    // with versions that will completely satisfy the peer deps rules, so we
-    // use the "legacy" behavior which makes a best effort.
-    let mut subproc = Exec::cmd(NPM).arg("i").arg("--legacy-peer-deps").cwd(&target);
-
-    subproc = if verbosity < 2 {
-        subproc.stdout(NullFile).stderr(NullFile)
+    let mut install_result = Ok(());
+    if install_args.is_empty() {
+        report.install = Some(InstallResult {
+            command: None,
+            status: "skipped",
+            error: None,
+        });
     } else {
-        subproc
-    };
-    info!("    running package install...");
-    let exit_status = subproc.join()?;
+        // .npmrc covers npm and pnpm, but yarn only reads the registry from it.
+        let mut args: Vec<String> = install_args.iter().map(|arg| arg.to_string()).collect();
+        if let Some(registry) = &updated_settings.registry {
+            args.push(format!("--registry={}", registry));
+        }
+        if updated_settings.offline.unwrap_or(false) {
+            args.push("--offline".to_string());
+        } else if updated_settings.prefer_offline.unwrap_or(false) {
+            args.push("--prefer-offline".to_string());
+        }
+        if package_manager == PackageManager::Npm && updated_settings.legacy_peer_deps() {
+            args.push("--legacy-peer-deps".to_string());
+        }
+        args.extend(updated_settings.install_args.iter().flatten().cloned());
+
+        let label = format!("{} {}", package_manager, args.join(" "));
+        info!("    running {}...", label);
+        let command = Exec::cmd(package_manager.command()).args(&args);
+        install_result = exec::run(command, &label, &target, verbosity).context(Failure::Install);
+        if install_result.is_ok() {
+            // Best effort: without it, the next run just installs again.
+            if let Err(err) = std::fs::write(&record, serde_json::to_vec(&installing)?) {
+                debug!("failed to write {:?}: {}", record, err);
+            }
+        }
+        report.install = Some(InstallResult {
+            command: Some(label),
+            status: if install_result.is_ok() { "succeeded" } else { "failed" },
+            error: install_result.as_ref().err().map(|err| format!("{:#}", err)),
+        });
+    }
 
-    match exit_status {
-        ExitStatus::Exited(0) => {
+    let features = updated_settings.features();
+    report.features.enabled = features.iter().map(|feature| feature.to_string()).collect();
+    report.features.disabled = settings
+        .features()
+        .into_iter()
+        .filter(|feature| !features.contains(feature))
+        .map(String::from)
+        .collect();
+
+    match flags.output {
+        // Emitted even if the install failed, so tooling can see how.
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Text if install_result.is_ok() => {
             warn!("Boltzmann@{} with:", version.blue().bold());
-            let features = updated_settings.features();
             print_table(features, 8, 3);
-            Ok(())
         }
-        _ => Err(anyhow!("npm install exited with non-zero status; run by hand to diagnose.")),
+        OutputFormat::Text => {}
+    }
+    install_result
+}
+
+// What the last successful install put in node_modules, so the next run can tell whether it's
+// still current. It goes away with node_modules.
+const INSTALL_RECORD: &str = "node_modules/.boltzmann-install.json";
+
+#[derive(Debug, PartialEq)]
+enum InstallStep {
+    Skip,
+    Ci,
+    Install,
+}
+
+// `installing` is what package.json asks for now and `last_installed` is the install record, if
+// node_modules has one. `unchanged` means this run left the dependencies alone, so a lockfile
+// still matches them.
+fn install_step(
+    installing: &Value,
+    last_installed: Option<&Value>,
+    unchanged: bool,
+    locked: bool,
+) -> InstallStep {
+    match last_installed {
+        Some(last) if last == installing => InstallStep::Skip,
+        None if unchanged && locked => InstallStep::Ci,
+        _ => InstallStep::Install,
     }
 }
 
@@ -672,3 +1606,90 @@ fn verify_app() {
     let app = Flags::command();
     app.debug_assert()
 }
+
+#[test]
+fn check_resolve_positionals() {
+    let resolve = |args: &[&str]| {
+        let mut flags = Flags::try_parse_from(std::iter::once("boltzmann").chain(args.iter().copied()))
+            .unwrap();
+        flags.resolve_positionals().map(|_| flags)
+    };
+
+    let flags = resolve(&["redis", "pg", "my-service", "hc=off", "ts=true"]).unwrap();
+    assert_eq!(flags.destination, PathBuf::from("my-service"));
+    assert!(matches!(flags.redis, Some(None)));
+    assert!(matches!(flags.postgres, Some(None)));
+    assert!(matches!(flags.honeycomb, Some(Some(Flipper::Off))));
+    assert!(matches!(flags.typescript, Some(Some(Flipper::On))));
+
+    // A feature name written as a path is the destination.
+    let flags = resolve(&["./redis", "githubci-release"]).unwrap();
+    assert_eq!(flags.destination, PathBuf::from("./redis"));
+    assert!(flags.redis.is_none());
+    assert!(matches!(flags.githubci_release, Some(None)));
+
+    for bad in [&["one", "two"][..], &["redis=maybe"], &["nope=off"]] {
+        let err = resolve(bad).err().unwrap();
+        assert_eq!(errors::exit_code(&err), 2, "{:?}", bad);
+    }
+}
+
+#[test]
+fn check_install_step() {
+    let installing = json!({ "packageManager": "npm", "dependencies": [{ "redis": "^3.1.0" }] });
+    let other = json!({ "packageManager": "npm", "dependencies": [{ "redis": "^4.0.0" }] });
+
+    // Whatever this run did to package.json, node_modules already has what it asks for.
+    assert_eq!(install_step(&installing, Some(&installing), false, true), InstallStep::Skip);
+    assert_eq!(install_step(&installing, Some(&installing), true, false), InstallStep::Skip);
+
+    // No node_modules: trust the lockfile only if this run left the dependencies alone.
+    assert_eq!(install_step(&installing, None, true, true), InstallStep::Ci);
+    assert_eq!(install_step(&installing, None, false, true), InstallStep::Install);
+    assert_eq!(install_step(&installing, None, true, false), InstallStep::Install);
+
+    // node_modules has something else, even if package.json was changed by hand.
+    assert_eq!(install_step(&installing, Some(&other), true, true), InstallStep::Install);
+    assert_eq!(install_step(&installing, Some(&other), false, true), InstallStep::Install);
+}
+
+#[test]
+fn check_overrides_per_package_manager() {
+    let package_json = || -> PackageJson {
+        serde_json::from_value(json!({
+            "dependencies": { "@opentelemetry/api": "^1.0.1" },
+            "pnpm": { "neverBuiltDependencies": [] },
+        }))
+        .unwrap()
+    };
+    let overrides: serde_json::Map<String, Value> = serde_json::from_value(json!({
+        "@opentelemetry/api": "$@opentelemetry/api",
+        "left-pad": "1.3.0",
+    }))
+    .unwrap();
+
+    // npm reads `$name` itself.
+    let mut npm = package_json();
+    npm.set_overrides(PackageManager::Npm, overrides.clone());
+    assert_eq!(npm.overrides.as_ref(), Some(&overrides));
+    assert_eq!(npm.take_overrides(PackageManager::Npm), overrides);
+
+    // pnpm and yarn get the range, next to any settings of theirs, and it reads back as `$name`.
+    let mut pnpm = package_json();
+    pnpm.set_overrides(PackageManager::Pnpm, overrides.clone());
+    assert_eq!(pnpm.rest["pnpm"]["overrides"]["@opentelemetry/api"], "^1.0.1");
+    assert_eq!(pnpm.take_overrides(PackageManager::Pnpm), overrides);
+    assert_eq!(pnpm.rest["pnpm"], json!({ "neverBuiltDependencies": [] }));
+
+    let mut yarn = package_json();
+    yarn.set_overrides(PackageManager::Yarn, overrides.clone());
+    assert_eq!(yarn.rest["resolutions"]["@opentelemetry/api"], "^1.0.1");
+    assert!(yarn.overrides.is_none());
+    assert_eq!(yarn.take_overrides(PackageManager::Yarn), overrides);
+    assert!(!yarn.rest.contains_key("resolutions"));
+
+    // Nothing to write leaves the fields out.
+    let mut empty = package_json();
+    empty.set_overrides(PackageManager::Yarn, Default::default());
+    assert!(!empty.rest.contains_key("resolutions"));
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use anyhow::{Context as ErrorContext, Result};
+
+/// Set or remove `key=value` lines in the project's .npmrc, returning true if it changed. An
+/// entry of None removes its key; keys we aren't given are left alone.
+pub fn update(dir: &Path, entries: &[(&str, Option<String>)]) -> Result<bool> {
+    let path = dir.join(".npmrc");
+    let existing = std::fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<String> = existing.lines().map(String::from).collect();
+
+    for (key, value) in entries {
+        let position = lines.iter().position(|line| line_key(line) == Some(key));
+        match (position, value) {
+            (Some(idx), Some(value)) => lines[idx] = format!("{}={}", key, value),
+            (None, Some(value)) => lines.push(format!("{}={}", key, value)),
+            (Some(idx), None) => {
+                lines.remove(idx);
+            }
+            (None, None) => {}
+        }
+    }
+
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        if existing.is_empty() {
+            return Ok(false);
+        }
+        std::fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+        return Ok(true);
+    }
+
+    let updated = format!("{}\n", lines.join("\n"));
+    if updated == existing {
+        return Ok(false);
+    }
+
+    std::fs::write(&path, updated).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(true)
+}
+
+fn line_key(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once('=')?;
+    Some(key.trim())
+}
+
+#[test]
+fn check_update() {
+    let dir = std::env::temp_dir().join(format!("boltzmann-npmrc-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".npmrc");
+    let read = || std::fs::read_to_string(&path).unwrap();
+
+    // Others' keys survive; ours are replaced in place or appended.
+    std::fs::write(&path, "registry=https://old.example.com\n//old.example.com/:_authToken=x\n")
+        .unwrap();
+    let registry = Some("https://npm.example.com".to_string());
+    assert!(update(&dir, &[("registry", registry), ("offline", Some("true".into()))]).unwrap());
+    assert_eq!(
+        read(),
+        "registry=https://npm.example.com\n//old.example.com/:_authToken=x\noffline=true\n"
+    );
+    let registry = Some("https://npm.example.com".to_string());
+    assert!(!update(&dir, &[("registry", registry)]).unwrap());
+
+    assert!(update(&dir, &[("registry", None), ("legacy-peer-deps", None)]).unwrap());
+    assert_eq!(read(), "//old.example.com/:_authToken=x\noffline=true\n");
+
+    // Removing the last entry removes the file, and nothing to write doesn't create one.
+    std::fs::write(&path, "offline=true\n").unwrap();
+    assert!(update(&dir, &[("offline", None)]).unwrap());
+    assert!(!path.exists());
+    assert!(!update(&dir, &[("offline", None)]).unwrap());
+    assert!(!path.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
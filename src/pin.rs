@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use anyhow::{Context as ErrorContext, Result};
+
+use super::settings::NodeManager;
+
+// Volta and asdf won't accept a bare major in their pins. These are the last releases of each
+// major in `settings::NODE_VERSIONS`.
+fn latest_release(major: &str) -> String {
+    match major {
+        "14" => "14.21.3".to_string(),
+        "16" => "16.20.2".to_string(),
+        "18" => "18.20.8".to_string(),
+        _ => format!("{}.0.0", major),
+    }
+}
+
+fn has_major(version: &str, major: &str) -> bool {
+    version.trim().trim_start_matches('v').split('.').next() == Some(major)
+}
+
+/// The exact version to pin for `major`, keeping an existing pin if it's already on that major.
+pub fn exact_version(existing: Option<&str>, major: &str) -> String {
+    match existing {
+        Some(version) if has_major(version, major) && version.split('.').count() == 3 => {
+            version.trim().to_string()
+        }
+        _ => latest_release(major),
+    }
+}
+
+/// The file `manager` keeps its pin in, if it isn't package.json.
+pub fn pin_file_name(manager: NodeManager) -> Option<&'static str> {
+    match manager {
+        NodeManager::Nvm => Some(".nvmrc"),
+        NodeManager::Fnm => Some(".node-version"),
+        NodeManager::Asdf => Some(".tool-versions"),
+        NodeManager::Volta | NodeManager::Unmanaged => None,
+    }
+}
+
+/// Write the pin file for `manager`, returning true if it changed. Volta keeps its pin in
+/// package.json, so it's handled along with the rest of that file.
+pub fn pin(dir: &Path, manager: NodeManager, major: &str) -> Result<bool> {
+    match (manager, pin_file_name(manager)) {
+        (NodeManager::Asdf, Some(name)) => pin_tool_versions(&dir.join(name), major),
+        (_, Some(name)) => pin_file(&dir.join(name), major),
+        (_, None) => Ok(false),
+    }
+}
+
+/// Remove the pin `manager` left behind, returning true if there was one.
+pub fn unpin(dir: &Path, manager: NodeManager) -> Result<bool> {
+    match (manager, pin_file_name(manager)) {
+        (NodeManager::Asdf, Some(name)) => unpin_tool_versions(&dir.join(name)),
+        (_, Some(name)) => remove_file(&dir.join(name)),
+        (_, None) => Ok(false),
+    }
+}
+
+fn pin_file(path: &Path, major: &str) -> Result<bool> {
+    let existing = std::fs::read_to_string(path).ok();
+    if let Some(version) = &existing {
+        if has_major(version, major) {
+            return Ok(false);
+        }
+    }
+
+    std::fs::write(path, format!("{}\n", major))
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(true)
+}
+
+fn remove_file(path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {:?}", path))?;
+    Ok(true)
+}
+
+// .tool-versions is shared with every other tool asdf manages, so only touch the nodejs line.
+fn pin_tool_versions(path: &Path, major: &str) -> Result<bool> {
+    let existing = std::fs::read_to_string(path).unwrap_or_default();
+    let mut lines: Vec<String> = existing.lines().map(String::from).collect();
+
+    let current = lines
+        .iter()
+        .position(|line| line.split_whitespace().next() == Some("nodejs"));
+    let current_version = current.and_then(|idx| lines[idx].split_whitespace().nth(1));
+    let line = format!("nodejs {}", exact_version(current_version, major));
+
+    match current {
+        Some(idx) if lines[idx] == line => return Ok(false),
+        Some(idx) => lines[idx] = line,
+        None => lines.push(line),
+    }
+
+    std::fs::write(path, format!("{}\n", lines.join("\n")))
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(true)
+}
+
+fn unpin_tool_versions(path: &Path) -> Result<bool> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(_) => return Ok(false),
+    };
+
+    let lines: Vec<&str> = existing
+        .lines()
+        .filter(|line| line.split_whitespace().next() != Some("nodejs"))
+        .collect();
+
+    if lines.len() == existing.lines().count() {
+        return Ok(false);
+    }
+
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return remove_file(path);
+    }
+
+    std::fs::write(path, format!("{}\n", lines.join("\n")))
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(true)
+}
+
+#[test]
+fn check_tool_versions() {
+    let dir = std::env::temp_dir().join(format!("boltzmann-pin-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".tool-versions");
+    let read = || std::fs::read_to_string(&path).unwrap();
+
+    // Only the nodejs line changes; the other tools' lines stay where they are.
+    std::fs::write(&path, "ruby 3.2.2\nnodejs 16.20.2\npython 3.11.4\n").unwrap();
+    assert!(pin(&dir, NodeManager::Asdf, "18").unwrap());
+    assert_eq!(read(), "ruby 3.2.2\nnodejs 18.20.8\npython 3.11.4\n");
+
+    // An exact pin on the right major is kept, whichever release it is.
+    std::fs::write(&path, "nodejs 18.12.0\nruby 3.2.2\n").unwrap();
+    assert!(!pin(&dir, NodeManager::Asdf, "18").unwrap());
+    assert_eq!(read(), "nodejs 18.12.0\nruby 3.2.2\n");
+
+    // Appended when missing.
+    std::fs::write(&path, "ruby 3.2.2\n").unwrap();
+    assert!(pin(&dir, NodeManager::Asdf, "16").unwrap());
+    assert_eq!(read(), "ruby 3.2.2\nnodejs 16.20.2\n");
+
+    // Unpinning drops only the nodejs line, and the file once nothing else is left.
+    assert!(unpin(&dir, NodeManager::Asdf).unwrap());
+    assert_eq!(read(), "ruby 3.2.2\n");
+    assert!(!unpin(&dir, NodeManager::Asdf).unwrap());
+    std::fs::write(&path, "nodejs 16.20.2\n").unwrap();
+    assert!(unpin(&dir, NodeManager::Asdf).unwrap());
+    assert!(!path.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,49 @@
+use super::config::Config;
+use super::Settings;
+
+// Presets every project gets. The user's config can redefine them, and a project's settings can
+// redefine those in turn.
+const BUILTIN: &[(&str, &[&str])] = &[
+    ("api", &["honeycomb", "jwt", "ping", "postgres", "status"]),
+    (
+        "website",
+        &[
+            "csrf",
+            "esbuild",
+            "jwt",
+            "livereload",
+            "oauth",
+            "ping",
+            "staticfiles",
+            "status",
+            "templates",
+        ],
+    ),
+];
+
+/// The features the preset called `name` turns on, as words like `redis` or `githubci=off`.
+pub fn lookup(name: &str, settings: &Settings, config: &Config) -> Option<Vec<String>> {
+    if let Some(features) = settings.presets.as_ref().and_then(|presets| presets.get(name)) {
+        return Some(features.clone());
+    }
+    if let Some(features) = config.presets.get(name) {
+        return Some(features.clone());
+    }
+    BUILTIN
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, features)| features.iter().map(|feature| feature.to_string()).collect())
+}
+
+/// Every preset `lookup` knows about, sorted.
+pub fn names(settings: &Settings, config: &Config) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .chain(config.presets.keys().cloned())
+        .chain(settings.presets.iter().flat_map(|presets| presets.keys().cloned()))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
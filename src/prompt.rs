@@ -0,0 +1,109 @@
+use std::io::prelude::*;
+
+use anyhow::Result;
+use log::{info, warn};
+use owo_colors::OwoColorize;
+
+use serde_json::Value;
+
+use super::features::FEATURES;
+use super::print_table;
+use super::{Flags, Flipper, Settings};
+
+// None at end of input, so a closed stdin reads as "no" rather than looping forever.
+fn read_answer(question: &str) -> Result<Option<String>> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+    let mut buffer = String::new();
+    if std::io::stdin().read_line(&mut buffer)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(buffer.trim().to_lowercase()))
+}
+
+/// Ask a yes or no question; anything but y or yes is a no.
+pub fn confirm(question: &str) -> Result<bool> {
+    let answer = read_answer(&format!("{} (y/n): ", question))?;
+    Ok(matches!(answer.as_deref(), Some("y") | Some("yes")))
+}
+
+/// Show the features `settings` has enabled as a checklist and let the user toggle them. The
+/// changes are recorded in `flags` as if they'd been passed on the command line. Returns false if
+/// the user backs out.
+pub fn pick_features(settings: &Settings, flags: &mut Flags) -> Result<bool> {
+    // Selftest is for developing Boltzmann itself.
+    let choices: Vec<&str> = FEATURES
+        .iter()
+        .copied()
+        .filter(|feature| *feature != "selftest")
+        .collect();
+    let current = serde_json::to_value(settings)?;
+    let was_enabled = |feature: &str| current[feature] == Value::Bool(true);
+    let mut enabled: Vec<bool> = choices.iter().map(|feature| was_enabled(feature)).collect();
+
+    loop {
+        let checklist: Vec<String> = choices
+            .iter()
+            .zip(&enabled)
+            .enumerate()
+            .map(|(idx, (feature, on))| {
+                format!("{} {:>2} {}", if *on { "[x]" } else { "[ ]" }, idx + 1, feature)
+            })
+            .collect();
+        print_table(checklist, 4, 4);
+
+        let answer = match read_answer("Toggle features by number or name, or press enter when done: ")? {
+            Some(answer) => answer,
+            None => return Ok(false),
+        };
+        if answer.is_empty() {
+            break;
+        }
+        for word in answer.split(|ch: char| ch == ',' || ch.is_whitespace()) {
+            if word.is_empty() {
+                continue;
+            }
+            let idx = match word.parse::<usize>() {
+                Ok(number) if (1..=choices.len()).contains(&number) => Some(number - 1),
+                Ok(_) => None,
+                Err(_) => choices
+                    .iter()
+                    .position(|feature| *feature == word.replace('-', "_")),
+            };
+            match idx {
+                Some(idx) => enabled[idx] = !enabled[idx],
+                None => warn!("    no feature {}", word.bold()),
+            }
+        }
+    }
+
+    let mut preview = vec![];
+    for (feature, on) in choices.iter().zip(enabled) {
+        if on == was_enabled(feature) {
+            continue;
+        }
+        let flag = flags
+            .feature_mut(feature)
+            .expect("every listed feature has a flag");
+        if on {
+            *flag = Some(Some(Flipper::On));
+            preview.push(format!("--{}", feature.replace('_', "-")));
+        } else {
+            *flag = Some(Some(Flipper::Off));
+            preview.push(format!("--{}=off", feature.replace('_', "-")));
+        }
+    }
+
+    if preview.is_empty() {
+        info!("Keeping the current features.");
+    } else {
+        info!(
+            "Equivalent to: {}",
+            format!("boltzmann {} {}", flags.destination.display(), preview.join(" "))
+                .bold()
+                .blue()
+        );
+    }
+    confirm("Continue?")
+}
@@ -10,7 +10,8 @@ use std::collections::HashSet;
 use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
 #[cfg(target_os = "windows")]
 use std::os::windows::fs::OpenOptionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context as ErrorContext, Result};
 use include_dir::{include_dir, Dir};
@@ -19,6 +20,8 @@ use serde::Deserialize;
 use serde_json::Value;
 use tera::{Context, Tera};
 
+use super::dependencies;
+use super::report::Report;
 use super::Settings;
 use super::When;
 
@@ -226,14 +229,16 @@ impl Node {
         mode: u32,
         parents: &mut Vec<String>,
         settings: &Settings,
+        report: &mut Report,
     ) -> Result<Option<String>> {
         match self {
-            Node::Dir(spec) => render_dir(spec, cwd, mode, parents, settings),
+            Node::Dir(spec) => render_dir(spec, cwd, mode, parents, settings, report),
             Node::File(spec) => Ok(Some(spec.contents)),
             Node::Template(spec) => {
                 let target = parents.join("/");
                 let mut context: Context = settings.clone().into();
                 context.insert("filename", &target[..]);
+                context.insert("managed_dependencies", &dependencies::managed_names(settings)?);
                 Ok(Some(TEMPLATES.render(&spec.template_name[..], &context)?))
             }
         }
@@ -246,6 +251,7 @@ fn render_dir(
     mode: u32,
     parents: &mut Vec<String>,
     settings: &Settings,
+    report: &mut Report,
 ) -> Result<Option<String>> {
     trace!("        entering {}", cwd.to_str().unwrap().blue());
     let mut db = std::fs::DirBuilder::new();
@@ -278,6 +284,14 @@ fn render_dir(
                 cloned_cwd.pop();
             }
 
+            // ...then skip what depends on files the developer provides.
+            for path in &preconditions.if_present {
+                if !cwd.join(path).exists() {
+                    trace!("        skipping {}; {} not found", basename, path);
+                    continue 'next;
+                }
+            }
+
             let wants_item = preconditions.are_satisfied_by(&mapped);
             if wants_item {
                 trace!("        prereqs met for {}", basename.blue().bold());
@@ -289,6 +303,9 @@ fn render_dir(
                         "        {} left in place; `git rm` to remove files you no longer need",
                         basename.blue().bold()
                     );
+                    parents.push(basename.clone());
+                    report.file(parents.join("/"), "kept");
+                    parents.pop();
                 }
                 cwd.pop();
                 continue 'next;
@@ -300,8 +317,15 @@ fn render_dir(
         parents.push(basename.clone());
 
         // failure to render is fatal.
-        if let Some(data) = node.render(cwd, mode, parents, settings)? {
+        if let Some(data) = node.render(cwd, mode, parents, settings, report)? {
             debug!("        rendering {}", basename.bold().blue());
+            let action = match std::fs::read(&cwd) {
+                Ok(existing) if existing == data.as_bytes() => "unchanged",
+                Ok(_) => "updated",
+                Err(_) => "created",
+            };
+            report.file(parents.join("/"), action);
+
             let mut oo = std::fs::OpenOptions::new();
 
             oo.create(true).truncate(true).write(true);
@@ -323,11 +347,60 @@ fn render_dir(
     Ok(None)
 }
 
-pub fn scaffold(cwd: &mut PathBuf, settings: &Settings) -> Result<Option<String>> {
+pub fn scaffold(
+    cwd: &mut PathBuf,
+    settings: &Settings,
+    report: &mut Report,
+) -> Result<Option<String>> {
     info!("    writing boltzmann files...");
     let root_node: Node = ron::de::from_str(include_str!("dirspec.ron"))?;
     let mut parents = Vec::new();
-    root_node.render(cwd, 0o777, &mut parents, settings)?;
+    root_node.render(cwd, 0o777, &mut parents, settings, report)?;
 
     Ok(None)
 }
+
+// Names we won't write a LICENSE over, whatever they contain.
+const LICENSE_FILES: &[&str] = &["LICENSE", "LICENSE.md", "LICENSE.txt", "LICENCE", "COPYING"];
+
+/// Write a LICENSE file for an SPDX `license` we have the text of, unless the project already has
+/// one. Returns false if we don't know the license.
+pub fn license(cwd: &Path, license: &str, holder: &str, report: &mut Report) -> Result<bool> {
+    let template = format!("licenses/{}", license);
+    if TEMPLATES_DIR.get_file(&template).is_none() {
+        return Ok(false);
+    }
+
+    if let Some(existing) = LICENSE_FILES.iter().find(|name| cwd.join(name).exists()) {
+        debug!("        {} exists; leaving it alone", existing);
+        report.file(*existing, "kept");
+        return Ok(true);
+    }
+
+    let mut context = Context::new();
+    context.insert("year", &current_year());
+    context.insert("holder", holder);
+    let contents = TEMPLATES.render(&template, &context)?;
+
+    let path = cwd.join("LICENSE");
+    info!("    writing {} LICENSE...", license.bold().blue());
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+    report.file("LICENSE", "created");
+    Ok(true)
+}
+
+// The UTC year, from days since the epoch; see http://howardhinnant.github.io/date_algorithms.html
+fn current_year() -> i64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0);
+    let days = secs.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153; // counting from March
+    year_of_era + era * 400 + if month >= 10 { 1 } else { 0 }
+}
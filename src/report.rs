@@ -0,0 +1,100 @@
+use serde::Serialize;
+
+/// How the results of a run are presented.
+#[derive(Clone, Copy, Serialize, clap::ArgEnum, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored, human-readable logs
+    Text,
+    /// A single JSON document on stdout, and nothing else
+    Json,
+}
+
+/// Everything a run changed, for `--output json`. Actions are recorded alongside the log lines
+/// that describe them in text mode.
+#[derive(Serialize, Default, Debug)]
+pub struct Report {
+    pub previous_version: Option<String>,
+    pub version: String,
+    pub features: FeatureChanges,
+    pub files: Vec<FileAction>,
+    pub dependencies: Vec<DependencyAction>,
+    pub scripts: Vec<ScriptAction>,
+    pub install: Option<InstallResult>,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct FeatureChanges {
+    /// Every feature enabled after this run.
+    pub enabled: Vec<String>,
+    /// Features this run turned off.
+    pub disabled: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileAction {
+    pub path: String,
+    /// One of created, updated, unchanged or kept, for files left in place after their feature
+    /// was turned off and existing licenses.
+    pub action: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DependencyAction {
+    pub name: String,
+    /// The package.json field the dependency lives in, e.g. devDependencies.
+    pub field: &'static str,
+    /// One of added, updated, removed, moved or kept.
+    pub action: &'static str,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScriptAction {
+    pub name: String,
+    /// Either set or kept.
+    pub action: &'static str,
+    pub value: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InstallResult {
+    pub command: Option<String>,
+    /// One of succeeded, failed or skipped.
+    pub status: &'static str,
+    pub error: Option<String>,
+}
+
+impl Report {
+    pub fn file(&mut self, path: impl Into<String>, action: &'static str) {
+        self.files.push(FileAction {
+            path: path.into(),
+            action,
+        });
+    }
+
+    pub fn dependency(
+        &mut self,
+        name: &str,
+        field: &'static str,
+        action: &'static str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) {
+        self.dependencies.push(DependencyAction {
+            name: name.to_string(),
+            field,
+            action,
+            from: from.map(String::from),
+            to: to.map(String::from),
+        });
+    }
+
+    pub fn script(&mut self, name: &str, action: &'static str, value: Option<&str>) {
+        self.scripts.push(ScriptAction {
+            name: name.to_string(),
+            action,
+            value: value.map(String::from),
+        });
+    }
+}
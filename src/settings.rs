@@ -1,14 +1,19 @@
 #![allow(clippy::option_option)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use tera::Context;
 
+use super::dependencies::DependencyOverride;
 use super::Flags;
 
+/// Node.js majors we know how to scaffold for: each one needs a matching `@tsconfig/nodeXX` base.
+pub const NODE_VERSIONS: &[&str] = &["14", "16", "18"];
+
 #[derive(Deserialize, Default)]
 pub struct When {
     #[serde(default)]
@@ -18,13 +23,15 @@ pub struct When {
     #[serde(default)]
     pub(crate) if_not_present: Vec<String>,
     #[serde(default)]
+    pub(crate) if_present: Vec<String>,
+    #[serde(default)]
     pub(crate) any_of: Vec<String>,
 }
 
 impl When {
     /// Returns true if the passed-in settings meet the conditions described by the When.
-    /// Does not consider `if_not_present` because the test for presence varies depending
-    /// on what the spec is for: files vs runscripts.
+    /// Does not consider `if_not_present` or `if_present` because the test for presence varies
+    /// depending on what the spec is for: files vs runscripts.
     pub fn are_satisfied_by(&self, settings: &serde_json::Value) -> bool {
         let false_sentinel = Value::Bool(false);
 
@@ -58,27 +65,53 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) version: Option<String>,
 
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) node_version: Option<String>,
 
+    // The package name, for templates that need to call the service something.
+    #[serde(skip)]
+    pub(crate) name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) compose: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) csrf: Option<bool>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) staticfiles: Option<bool>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) dependabot: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) esbuild: Option<bool>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) githubci: Option<bool>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) githubci_container: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) githubci_release: Option<bool>,
+
+    // Node.js majors for the CI test matrix; defaults to just node_version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ci_node_versions: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gitlabci: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) honeycomb: Option<bool>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) jwt: Option<bool>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) kubernetes: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) livereload: Option<bool>,
 
@@ -94,6 +127,9 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) redis: Option<bool>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) renovate: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) selftest: Option<bool>,
 
@@ -106,9 +142,45 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) typescript: Option<bool>,
 
+    // Install type definitions as devDependencies in TypeScript projects, too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) dev_typings: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) node_manager: Option<NodeManager>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) package_manager: Option<PackageManager>,
+
+    // Install options, also written to .npmrc so a manual install matches ours.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) registry: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) prefer_offline: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) offline: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) legacy_peer_deps: Option<bool>,
+
+    // Passed to the install command as-is; these don't go in .npmrc.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) install_args: Option<Vec<String>>,
+
+    // Superseded by node_manager; read so projects scaffolded with --volta keep their pin.
+    #[serde(skip_serializing)]
     pub(crate) volta: Option<bool>,
 
+    // Per-package overrides of the dependencies we manage, keyed by package name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) dependencies: Option<BTreeMap<String, DependencyOverride>>,
+
+    // This project's --preset groups, by name; they win over the user's and the built-in ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) presets: Option<BTreeMap<String, Vec<String>>>,
+
     #[serde(flatten)]
     pub(crate) rest: HashMap<String, Value>,
 }
@@ -131,6 +203,15 @@ impl Settings {
             }
         };
 
+        // For options that aren't features: off clears the setting rather than recording false.
+        let flip = |xs: &Option<Option<Flipper>>, default: &Option<bool>| -> Option<bool> {
+            match xs {
+                Some(None) | Some(Some(Flipper::On)) => Some(true),
+                Some(Some(Flipper::Off)) => None,
+                None => *default,
+            }
+        };
+
         let is_typescript = match &flags.typescript {
             Some(None) => true,
             Some(Some(Flipper::On)) => true,
@@ -138,75 +219,175 @@ impl Settings {
             None => self.typescript.unwrap_or(false),
         };
 
-        let is_volta = match &flags.volta {
-            Some(None) => true,
-            Some(Some(Flipper::On)) => true,
-            Some(Some(Flipper::Off)) => false,
-            None => self.volta.unwrap_or(false),
+        let node_manager = match (&flags.node_manager, &flags.volta) {
+            (Some(manager), _) => *manager,
+            (None, Some(None)) => NodeManager::Volta,
+            (None, Some(Some(Flipper::On))) => NodeManager::Volta,
+            (None, Some(Some(Flipper::Off))) => NodeManager::Unmanaged,
+            (None, None) => self.node_manager(),
         };
 
+        // GitHub CI is on by default, which is just noise for projects hosted on GitLab. Turn it
+        // off when someone asks for GitLab CI, unless they asked for GitHub CI too.
+        let gitlabci = cast(&flags.gitlabci, &self.gitlabci, flags.all);
+        let switching_to_gitlab =
+            gitlabci == Some(true) && self.gitlabci != Some(true) && !flags.all && !flags.selftest;
+
         Settings {
             // website features, grouped
-            csrf: cast(&flags.csrf, &self.csrf, flags.all || flags.website),
+            csrf: cast(&flags.csrf, &self.csrf, flags.all),
             staticfiles: cast(
                 &flags.staticfiles,
                 &self.staticfiles,
-                flags.all || flags.website,
+                flags.all,
             ),
-            esbuild: cast(&flags.esbuild, &self.esbuild, flags.all || flags.website),
-            jwt: cast(&flags.jwt, &self.jwt, flags.all || flags.website),
+            esbuild: cast(&flags.esbuild, &self.esbuild, flags.all),
+            jwt: cast(&flags.jwt, &self.jwt, flags.all),
             livereload: cast(
                 &flags.livereload,
                 &self.livereload,
-                flags.all || flags.website,
+                flags.all,
             ),
-            oauth: cast(&flags.oauth, &self.oauth, flags.all || flags.website),
-            ping: cast(&flags.ping, &self.ping, flags.all || flags.website),
-            status: cast(&flags.status, &self.status, flags.all || flags.website),
+            oauth: cast(&flags.oauth, &self.oauth, flags.all),
+            ping: cast(&flags.ping, &self.ping, flags.all),
+            status: cast(&flags.status, &self.status, flags.all),
             templates: cast(
                 &flags.templates,
                 &self.templates,
-                flags.all || flags.website,
+                flags.all,
             ),
 
             // non-website features
-            githubci: cast(&flags.githubci, &self.githubci, flags.all),
+            compose: cast(&flags.compose, &self.compose, flags.all),
+            dependabot: cast(&flags.dependabot, &self.dependabot, flags.all),
+            githubci: if flags.githubci.is_none() && switching_to_gitlab {
+                Some(false)
+            } else {
+                cast(&flags.githubci, &self.githubci, flags.all)
+            },
+            githubci_container: cast(
+                &flags.githubci_container,
+                &self.githubci_container,
+                flags.all,
+            ),
+            githubci_release: cast(&flags.githubci_release, &self.githubci_release, flags.all),
+            gitlabci,
+            ci_node_versions: flags
+                .ci_node
+                .clone()
+                .or_else(|| self.ci_node_versions.clone()),
             honeycomb: cast(&flags.honeycomb, &self.honeycomb, flags.all),
+            kubernetes: cast(&flags.kubernetes, &self.kubernetes, flags.all),
             postgres: cast(&flags.postgres, &self.postgres, flags.all),
             redis: cast(&flags.redis, &self.redis, flags.all),
+            renovate: cast(&flags.renovate, &self.renovate, flags.all),
 
             // oddballs:
             typescript: if is_typescript { Some(true) } else { None },
+            dev_typings: flip(&flags.dev_typings, &self.dev_typings),
             version: Some(version),
-            node_version: Some(node_version),
-            volta: if is_volta { Some(true) } else { None},
+            node_version: flags
+                .node
+                .clone()
+                .or_else(|| self.node_version.clone())
+                .or(Some(node_version)),
+            name: None,
+            node_manager: if node_manager == NodeManager::Unmanaged {
+                None
+            } else {
+                Some(node_manager)
+            },
+            volta: None,
+            // Filled in from the lockfile by the caller if nobody has chosen yet.
+            package_manager: flags.package_manager.or(self.package_manager),
+
+            // An empty --registry or --install-args clears the saved value.
+            registry: match &flags.registry {
+                Some(registry) if registry.is_empty() => None,
+                Some(registry) => Some(registry.clone()),
+                None => self.registry.clone(),
+            },
+            prefer_offline: flip(&flags.prefer_offline, &self.prefer_offline),
+            offline: flip(&flags.offline, &self.offline),
+            // Saved when turned off, since it's on by default.
+            legacy_peer_deps: match &flags.legacy_peer_deps {
+                Some(Some(Flipper::Off)) => Some(false),
+                _ => flip(&flags.legacy_peer_deps, &self.legacy_peer_deps),
+            },
+            install_args: match &flags.install_args {
+                Some(args) if args.trim().is_empty() => None,
+                Some(args) => Some(args.split_whitespace().map(String::from).collect()),
+                None => self.install_args.clone(),
+            },
 
             selftest: if flags.selftest { Some(true) } else { None },
+            dependencies: self.dependencies.clone(),
+            presets: self.presets.clone(),
             rest: HashMap::new(),
         }
     }
 
+    pub fn package_manager(&self) -> PackageManager {
+        self.package_manager.unwrap_or(PackageManager::Npm)
+    }
+
+    // Not every OpenTelemetry package's peer ranges can be satisfied at once, overrides or not,
+    // so npm ignores peer conflicts unless a project turns that off.
+    pub fn legacy_peer_deps(&self) -> bool {
+        self.legacy_peer_deps.unwrap_or(true)
+    }
+
+    pub fn node_manager(&self) -> NodeManager {
+        match (self.node_manager, self.volta) {
+            (Some(manager), _) => manager,
+            (None, Some(true)) => NodeManager::Volta,
+            _ => NodeManager::Unmanaged,
+        }
+    }
+
+    pub fn dependency_override(&self, name: &str) -> Option<&DependencyOverride> {
+        self.dependencies.as_ref()?.get(name)
+    }
+
     pub fn features(&self) -> Vec<&str> {
         let mut features = vec![];
         // I'm fairly horrified by this.
+        if self.compose.unwrap_or(false) {
+            features.push("compose");
+        }
         if self.csrf.unwrap_or(false) {
             features.push("csrf");
         }
         if self.staticfiles.unwrap_or(false) {
             features.push("staticfiles");
         }
+        if self.dependabot.unwrap_or(false) {
+            features.push("dependabot");
+        }
         if self.esbuild.unwrap_or(false) {
             features.push("esbuild");
         }
         if self.githubci.unwrap_or(false) {
             features.push("githubci");
         }
+        if self.githubci_container.unwrap_or(false) {
+            features.push("githubci_container");
+        }
+        if self.githubci_release.unwrap_or(false) {
+            features.push("githubci_release");
+        }
+        if self.gitlabci.unwrap_or(false) {
+            features.push("gitlabci");
+        }
         if self.honeycomb.unwrap_or(false) {
             features.push("honeycomb");
         }
         if self.jwt.unwrap_or(false) {
             features.push("jwt");
         }
+        if self.kubernetes.unwrap_or(false) {
+            features.push("kubernetes");
+        }
         if self.livereload.unwrap_or(false) {
             features.push("livereload");
         }
@@ -222,6 +403,9 @@ impl Settings {
         if self.redis.unwrap_or(false) {
             features.push("redis");
         }
+        if self.renovate.unwrap_or(false) {
+            features.push("renovate");
+        }
         if self.status.unwrap_or(false) {
             features.push("status");
         }
@@ -254,16 +438,26 @@ impl From<Settings> for Context {
         let mut ctxt = Context::new();
 
         ctxt.insert("staticfiles", &settings.staticfiles.unwrap_or(false));
+        ctxt.insert("compose", &settings.compose.unwrap_or(false));
         ctxt.insert("csrf", &settings.csrf.unwrap_or(false));
         ctxt.insert("githubci", &settings.githubci.unwrap_or(false));
+        ctxt.insert(
+            "githubci_container",
+            &settings.githubci_container.unwrap_or(false),
+        );
+        ctxt.insert("githubci_release", &settings.githubci_release.unwrap_or(false));
+        ctxt.insert("gitlabci", &settings.gitlabci.unwrap_or(false));
         ctxt.insert("honeycomb", &settings.honeycomb.unwrap_or(false));
+        ctxt.insert("dependabot", &settings.dependabot.unwrap_or(false));
         ctxt.insert("esbuild", &settings.esbuild.unwrap_or(false));
         ctxt.insert("jwt", &settings.jwt.unwrap_or(false));
+        ctxt.insert("kubernetes", &settings.kubernetes.unwrap_or(false));
         ctxt.insert("livereload", &settings.livereload.unwrap_or(false));
         ctxt.insert("oauth", &settings.oauth.unwrap_or(false));
         ctxt.insert("ping", &settings.ping.unwrap_or(false));
         ctxt.insert("postgres", &settings.postgres.unwrap_or(false));
         ctxt.insert("redis", &settings.redis.unwrap_or(false));
+        ctxt.insert("renovate", &settings.renovate.unwrap_or(false));
         ctxt.insert("status", &settings.status.unwrap_or(false));
         ctxt.insert("templates", &settings.templates.unwrap_or(false));
         ctxt.insert("typescript", &settings.typescript.unwrap_or(false));
@@ -274,18 +468,151 @@ impl From<Settings> for Context {
                 .version
                 .unwrap_or_else(|| "<unknown version>".to_string())[..],
         );
-        ctxt.insert("node_version", &settings.node_version.unwrap());
+        let node_version = settings.node_version.unwrap();
+        ctxt.insert(
+            "ci_node_versions",
+            &settings
+                .ci_node_versions
+                .unwrap_or_else(|| vec![node_version.clone()]),
+        );
+        ctxt.insert("node_version", &node_version);
+        let package_manager = settings.package_manager.unwrap_or(PackageManager::Npm);
+        ctxt.insert("package_manager", &package_manager.to_string());
+        ctxt.insert("lockfile", package_manager.lockfile());
+        ctxt.insert("ci_install", &package_manager.ci_install());
+        ctxt.insert(
+            "name",
+            &dns_label(settings.name.as_deref().unwrap_or("boltzmann-app")),
+        );
 
         ctxt
     }
 }
 
+// Package names may be scoped or contain characters kubernetes (and friends) won't accept as
+// object names, so squash them into a lowercase DNS label: `@corp/My_Service` becomes
+// `my-service`.
+fn dns_label(name: &str) -> String {
+    let unscoped = name.rsplit('/').next().unwrap_or(name);
+    let mut label = String::with_capacity(unscoped.len());
+    for ch in unscoped.chars() {
+        if ch.is_ascii_alphanumeric() {
+            label.push(ch.to_ascii_lowercase());
+        } else if !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label.truncate(63);
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        "boltzmann-app".to_string()
+    } else {
+        label.to_string()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, clap::ArgEnum, Debug)]
 pub enum Flipper {
     Off,
     On,
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, clap::ArgEnum, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeManager {
+    Volta,
+    Nvm,
+    Fnm,
+    Asdf,
+    #[clap(name = "none")]
+    #[serde(rename = "none")]
+    Unmanaged,
+}
+
+impl fmt::Display for NodeManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NodeManager::Volta => "volta",
+            NodeManager::Nvm => "nvm",
+            NodeManager::Fnm => "fnm",
+            NodeManager::Asdf => "asdf",
+            NodeManager::Unmanaged => "none",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, clap::ArgEnum, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+}
+
+impl PackageManager {
+    /// Guess from the lockfile in `dir`, if there is one.
+    pub fn detect(dir: &Path) -> Option<PackageManager> {
+        [PackageManager::Pnpm, PackageManager::Yarn, PackageManager::Npm]
+            .into_iter()
+            .find(|manager| dir.join(manager.lockfile()).exists())
+    }
+
+    /// The executable to run; on Windows these are batch file shims.
+    pub fn command(&self) -> String {
+        if cfg!(target_os = "windows") {
+            format!("{}.cmd", self)
+        } else {
+            self.to_string()
+        }
+    }
+
+    pub fn lockfile(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "package-lock.json",
+            PackageManager::Pnpm => "pnpm-lock.yaml",
+            PackageManager::Yarn => "yarn.lock",
+        }
+    }
+
+    /// The file a successful install leaves in node_modules.
+    pub fn install_marker(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "node_modules/.package-lock.json",
+            PackageManager::Pnpm => "node_modules/.modules.yaml",
+            PackageManager::Yarn => "node_modules/.yarn-integrity",
+        }
+    }
+
+    /// The arguments that install exactly what the lockfile says.
+    pub fn ci_install_args(&self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Npm => &["ci"],
+            PackageManager::Pnpm | PackageManager::Yarn => &["install", "--frozen-lockfile"],
+        }
+    }
+
+    /// The lockfile install as a command line, for CI and container builds.
+    pub fn ci_install(&self) -> String {
+        format!("{} {}", self, self.ci_install_args().join(" "))
+    }
+
+    /// Rewrite the `npm run` calls in a run script (as written in runscripts.ron) for this
+    /// package manager.
+    pub fn run_script(&self, script: &str) -> String {
+        script.replace("npm run ", &format!("{} run ", self))
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn => "yarn",
+        })
+    }
+}
+
 impl From<bool> for Flipper {
     fn from(v: bool) -> Self {
         if v {
@@ -319,3 +646,17 @@ impl std::str::FromStr for Flipper {
         })
     }
 }
+
+#[test]
+fn check_features_are_settings() {
+    use super::features::FEATURES;
+
+    for feature in FEATURES {
+        let mut settings = serde_json::Map::new();
+        settings.insert(feature.to_string(), Value::Bool(true));
+        let settings: Settings = serde_json::from_value(Value::Object(settings))
+            .unwrap_or_else(|err| panic!("{} isn't a boolean setting: {}", feature, err));
+        assert!(settings.rest.is_empty(), "{} isn't a field of Settings", feature);
+        assert_eq!(settings.features(), [*feature], "features() leaves out {}", feature);
+    }
+}